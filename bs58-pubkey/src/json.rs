@@ -0,0 +1,292 @@
+//! Allocation-free JSON writer for on-chain rendering.
+//!
+//! `serde_json::to_string` and `serde_pubkey` allocate a `String` per document and per key,
+//! which is expensive in BPF. `JsonWriter` instead writes straight into a caller-provided
+//! buffer, usually a `[u8; MAX_RETURN_DATA]`, and base58-encodes pubkeys in place.
+//!
+//! Output is compact and matches `serde_json::to_string` for the same fields in the same order.
+
+/// Longest base58 encoding of a 32 byte key
+pub const MAX_BASE58_PUBKEY_LEN: usize = 44;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonError {
+    /// The writer's buffer cannot hold the next token
+    BufferTooSmall,
+}
+
+/// Base58-encodes `key` into `out`, returning the number of bytes written
+pub fn encode_base58_into(key: &[u8], out: &mut [u8]) -> Result<usize, JsonError> {
+    bs58::encode(key)
+        .onto(out)
+        .map_err(|_| JsonError::BufferTooSmall)
+}
+
+/// Writes compact JSON into a fixed buffer.
+///
+/// Objects and arrays are opened and closed explicitly, and separators are inserted
+/// automatically. The writer does not check that keys and values alternate.
+pub struct JsonWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    needs_comma: bool,
+}
+
+impl<'a> JsonWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            len: 0,
+            needs_comma: false,
+        }
+    }
+
+    pub fn begin_object(&mut self) -> Result<(), JsonError> {
+        self.separator()?;
+        self.raw(b"{")?;
+        self.needs_comma = false;
+        Ok(())
+    }
+
+    pub fn end_object(&mut self) -> Result<(), JsonError> {
+        self.raw(b"}")?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    pub fn begin_array(&mut self) -> Result<(), JsonError> {
+        self.separator()?;
+        self.raw(b"[")?;
+        self.needs_comma = false;
+        Ok(())
+    }
+
+    pub fn end_array(&mut self) -> Result<(), JsonError> {
+        self.raw(b"]")?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    /// Writes an object key and the `:` that follows it
+    pub fn key(&mut self, key: &str) -> Result<(), JsonError> {
+        self.separator()?;
+        self.escaped(key)?;
+        self.raw(b":")?;
+        self.needs_comma = false;
+        Ok(())
+    }
+
+    pub fn str(&mut self, value: &str) -> Result<(), JsonError> {
+        self.separator()?;
+        self.escaped(value)?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    /// Writes a 32 byte key as a base58 string, encoded directly into the buffer
+    pub fn pubkey<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), JsonError> {
+        self.separator()?;
+        self.raw(b"\"")?;
+        let written = encode_base58_into(key.as_ref(), &mut self.buf[self.len..])?;
+        self.len += written;
+        self.raw(b"\"")?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    pub fn u64(&mut self, value: u64) -> Result<(), JsonError> {
+        self.separator()?;
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        let mut rest = value;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        self.raw(&digits[start..])?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    pub fn bool(&mut self, value: bool) -> Result<(), JsonError> {
+        self.separator()?;
+        self.raw(if value { b"true" } else { b"false" })?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    pub fn null(&mut self) -> Result<(), JsonError> {
+        self.separator()?;
+        self.raw(b"null")?;
+        self.needs_comma = true;
+        Ok(())
+    }
+
+    pub fn field_str(&mut self, key: &str, value: &str) -> Result<(), JsonError> {
+        self.key(key)?;
+        self.str(value)
+    }
+
+    pub fn field_pubkey<K: AsRef<[u8]>>(&mut self, key: &str, value: K) -> Result<(), JsonError> {
+        self.key(key)?;
+        self.pubkey(value)
+    }
+
+    pub fn field_u64(&mut self, key: &str, value: u64) -> Result<(), JsonError> {
+        self.key(key)?;
+        self.u64(value)
+    }
+
+    pub fn field_bool(&mut self, key: &str, value: bool) -> Result<(), JsonError> {
+        self.key(key)?;
+        self.bool(value)
+    }
+
+    /// Number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The JSON written so far
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only `&str` contents, ASCII escapes and base58 are ever written,
+        // and a failed write never leaves a partial utf8 sequence behind
+        core::str::from_utf8(self.as_bytes()).expect("JsonWriter only writes utf8")
+    }
+
+    fn separator(&mut self) -> Result<(), JsonError> {
+        if self.needs_comma {
+            self.raw(b",")?;
+        }
+        Ok(())
+    }
+
+    fn raw(&mut self, bytes: &[u8]) -> Result<(), JsonError> {
+        let end = self.len + bytes.len();
+        if end > self.buf.len() {
+            return Err(JsonError::BufferTooSmall);
+        }
+        self.buf[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Writes `value` as a quoted string, escaping it the same way `serde_json` does
+    fn escaped(&mut self, value: &str) -> Result<(), JsonError> {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+
+        self.raw(b"\"")?;
+        let bytes = value.as_bytes();
+        let mut start = 0;
+        for (i, &byte) in bytes.iter().enumerate() {
+            let escape: &[u8] = match byte {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                0x08 => b"\\b",
+                0x0c => b"\\f",
+                0x00..=0x1f => &[],
+                _ => continue,
+            };
+            self.raw(&bytes[start..i])?;
+            if escape.is_empty() {
+                self.raw(&[
+                    b'\\',
+                    b'u',
+                    b'0',
+                    b'0',
+                    HEX[(byte >> 4) as usize],
+                    HEX[(byte & 0xf) as usize],
+                ])?;
+            } else {
+                self.raw(escape)?;
+            }
+            start = i + 1;
+        }
+        self.raw(&bytes[start..])?;
+        self.raw(b"\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+
+    #[test]
+    fn matches_serde_json() {
+        let key = Pubkey::new_from_array([7; 32]);
+        let name = "quote \" slash \\ newline \n bell \u{7} é";
+
+        let mut buf = [0u8; 256];
+        let mut writer = JsonWriter::new(&mut buf);
+        writer.begin_object().unwrap();
+        writer.field_pubkey("owner", key).unwrap();
+        writer.field_str("name", name).unwrap();
+        writer.field_u64("num", u64::MAX).unwrap();
+        writer.field_bool("mutable", false).unwrap();
+        writer.key("tags").unwrap();
+        writer.begin_array().unwrap();
+        writer.u64(0).unwrap();
+        writer.null().unwrap();
+        writer.begin_object().unwrap();
+        writer.end_object().unwrap();
+        writer.end_array().unwrap();
+        writer.end_object().unwrap();
+
+        #[derive(serde::Serialize)]
+        struct Expected<'a> {
+            #[serde(with = "crate::serde_pubkey")]
+            owner: Pubkey,
+            name: &'a str,
+            num: u64,
+            mutable: bool,
+            tags: (u8, (), std::collections::BTreeMap<u8, u8>),
+        }
+        let expected = Expected {
+            owner: key,
+            name,
+            num: u64::MAX,
+            mutable: false,
+            tags: (0, (), Default::default()),
+        };
+        assert_eq!(writer.as_str(), serde_json::to_string(&expected).unwrap());
+    }
+
+    #[test]
+    fn reports_overflow() {
+        let mut buf = [0u8; 8];
+        let mut writer = JsonWriter::new(&mut buf);
+        writer.begin_object().unwrap();
+        assert_eq!(
+            writer.field_pubkey("k", Pubkey::new_from_array([9; 32])),
+            Err(JsonError::BufferTooSmall)
+        );
+        assert_eq!(
+            writer.field_str("k", "long value"),
+            Err(JsonError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn encodes_base58_in_place() {
+        let key = Pubkey::new_from_array([255; 32]);
+        let mut out = [0u8; MAX_BASE58_PUBKEY_LEN];
+        let len = encode_base58_into(key.as_ref(), &mut out).unwrap();
+        assert_eq!(&out[..len], key.to_string().as_bytes());
+    }
+}
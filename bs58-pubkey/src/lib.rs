@@ -1,6 +1,21 @@
+use anchor_lang::prelude::borsh;
 use anchor_lang::*;
 use serde::{self, Deserializer, Serializer};
 
+pub mod json;
+
+impl From<json::JsonError> for anchor_lang::prelude::ProgramError {
+    fn from(_: json::JsonError) -> Self {
+        anchor_lang::prelude::ProgramError::AccountDataTooSmall
+    }
+}
+
+impl From<json::JsonError> for anchor_lang::error::Error {
+    fn from(e: json::JsonError) -> Self {
+        anchor_lang::prelude::ProgramError::from(e).into()
+    }
+}
+
 /// HACK: in order to render Pubkey as base58 str, we need to implement custom serde
 /// otherwise it would render as array of bytes
 /// Credit: GPT-4
@@ -28,3 +43,208 @@ pub mod serde_pubkey {
         Pubkey::try_from_slice(&bytes).map_err(serde::de::Error::custom)
     }
 }
+
+/// Same as `serde_pubkey`, for `Option<Pubkey>` fields. `None` renders as `null`.
+///
+/// Add `#[serde(default)]` if the field may be missing when deserializing.
+pub mod serde_pubkey_option {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+
+    pub fn serialize<S>(
+        value: &Option<Pubkey>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde::Serialize::serialize(&value.map(Base58Pubkey), serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Option<Pubkey>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = <Option<Base58Pubkey> as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(value.map(Pubkey::from))
+    }
+}
+
+/// Same as `serde_pubkey`, for `Vec<Pubkey>` fields. Renders as an array of base58 strings.
+pub mod serde_pubkey_vec {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+
+    pub fn serialize<S>(value: &[Pubkey], serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(value.iter().copied().map(Base58Pubkey))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Vec<Pubkey>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = <Vec<Base58Pubkey> as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(value.into_iter().map(Pubkey::from).collect())
+    }
+}
+
+/// A `Pubkey` that serializes as a base58 string with serde, and as 32 bytes with borsh.
+///
+/// Use it where a `#[serde(with = ...)]` attribute cannot reach, such as the keys of a
+/// `BTreeMap<Base58Pubkey, _>` or the items of nested collections.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    AnchorSerialize,
+    AnchorDeserialize,
+)]
+pub struct Base58Pubkey(pub anchor_lang::prelude::Pubkey);
+
+impl From<anchor_lang::prelude::Pubkey> for Base58Pubkey {
+    fn from(value: anchor_lang::prelude::Pubkey) -> Self {
+        Base58Pubkey(value)
+    }
+}
+
+impl From<Base58Pubkey> for anchor_lang::prelude::Pubkey {
+    fn from(value: Base58Pubkey) -> Self {
+        value.0
+    }
+}
+
+impl std::ops::Deref for Base58Pubkey {
+    type Target = anchor_lang::prelude::Pubkey;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Base58Pubkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl serde::Serialize for Base58Pubkey {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_pubkey::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Base58Pubkey {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_pubkey::deserialize(deserializer).map(Base58Pubkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Asset {
+        #[serde(with = "serde_pubkey")]
+        owner: Pubkey,
+        #[serde(with = "serde_pubkey_option", default)]
+        delegate: Option<Pubkey>,
+        #[serde(with = "serde_pubkey_vec")]
+        creators: Vec<Pubkey>,
+        shares: BTreeMap<Base58Pubkey, u8>,
+    }
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    #[test]
+    fn renders_every_pubkey_as_base58() {
+        let asset = Asset {
+            owner: key(1),
+            delegate: Some(key(2)),
+            creators: vec![key(3), key(4)],
+            shares: BTreeMap::from([(Base58Pubkey(key(3)), 60), (Base58Pubkey(key(4)), 40)]),
+        };
+
+        let json = serde_json::to_value(&asset).unwrap();
+        assert_eq!(json["owner"], key(1).to_string());
+        assert_eq!(json["delegate"], key(2).to_string());
+        assert_eq!(
+            json["creators"],
+            serde_json::json!([key(3).to_string(), key(4).to_string()])
+        );
+        assert_eq!(json["shares"][key(3).to_string()], 60);
+
+        let decoded: Asset = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, asset);
+    }
+
+    #[test]
+    fn option_round_trips_none() {
+        let asset = Asset {
+            owner: key(1),
+            delegate: None,
+            creators: vec![],
+            shares: BTreeMap::new(),
+        };
+
+        let json = serde_json::to_value(&asset).unwrap();
+        assert!(json["delegate"].is_null());
+        assert_eq!(serde_json::from_value::<Asset>(json).unwrap(), asset);
+
+        let missing = serde_json::json!({
+            "owner": key(1).to_string(),
+            "creators": [],
+            "shares": {},
+        });
+        assert_eq!(serde_json::from_value::<Asset>(missing).unwrap(), asset);
+    }
+
+    #[test]
+    fn newtype_matches_serde_pubkey() {
+        #[derive(Serialize)]
+        struct Bare {
+            #[serde(with = "serde_pubkey")]
+            key: Pubkey,
+        }
+
+        let bare = serde_json::to_value(Bare { key: key(7) }).unwrap();
+        let newtype = serde_json::to_value(Base58Pubkey(key(7))).unwrap();
+        assert_eq!(bare["key"], newtype);
+    }
+
+    #[test]
+    fn newtype_borsh_is_raw_pubkey() {
+        let bytes = Base58Pubkey(key(9)).try_to_vec().unwrap();
+        assert_eq!(bytes, key(9).to_bytes());
+        assert_eq!(
+            Base58Pubkey::try_from_slice(&bytes).unwrap(),
+            Base58Pubkey(key(9))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_base58() {
+        assert!(serde_json::from_str::<Base58Pubkey>("\"0OIl\"").is_err());
+        assert!(serde_json::from_str::<Base58Pubkey>("\"abc\"").is_err());
+    }
+}
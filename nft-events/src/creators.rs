@@ -0,0 +1,208 @@
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::tlv::TlvError;
+
+/// Most creators an asset can list
+pub const MAX_CREATORS: usize = 5;
+
+/// Creator shares are basis points that must add up to 100%
+pub const TOTAL_CREATOR_SHARE_BPS: u16 = 10_000;
+
+/// `[address: 32][share_bps: u16 LE][verified: u8]`, the same in accounts and in the
+/// `creators` TLV entry
+pub const CREATOR_LEN: usize = 32 + 2 + 1;
+
+/// A creator of an asset and their share of its royalties
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, Serialize)]
+pub struct Creator {
+    #[serde(with = "bs58_pubkey::serde_pubkey")]
+    pub address: Pubkey,
+    pub share_bps: u16,
+    /// Set once the creator signs `sign_creator`
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatorError {
+    /// More than `MAX_CREATORS` creators
+    TooManyCreators,
+    /// Shares do not add up to `TOTAL_CREATOR_SHARE_BPS`
+    InvalidShares,
+    /// The same address is listed twice
+    DuplicateCreator,
+    /// The signer is not one of the creators
+    UnknownCreator,
+}
+
+impl From<CreatorError> for ProgramError {
+    fn from(_: CreatorError) -> Self {
+        ProgramError::InvalidArgument
+    }
+}
+
+impl From<CreatorError> for anchor_lang::error::Error {
+    fn from(e: CreatorError) -> Self {
+        ProgramError::from(e).into()
+    }
+}
+
+/// Checks a creators list: at most `MAX_CREATORS` distinct addresses whose shares add up
+/// to 100%. An asset without creators has an empty list.
+pub fn validate_creators(creators: &[Creator]) -> std::result::Result<(), CreatorError> {
+    if creators.is_empty() {
+        return Ok(());
+    }
+    if creators.len() > MAX_CREATORS {
+        return Err(CreatorError::TooManyCreators);
+    }
+    for (i, creator) in creators.iter().enumerate() {
+        if creators[..i].iter().any(|c| c.address == creator.address) {
+            return Err(CreatorError::DuplicateCreator);
+        }
+    }
+    let total: u32 = creators.iter().map(|c| u32::from(c.share_bps)).sum();
+    if total != u32::from(TOTAL_CREATOR_SHARE_BPS) {
+        return Err(CreatorError::InvalidShares);
+    }
+    Ok(())
+}
+
+/// Validates creators supplied at mint. Only `signer` starts out verified, every other
+/// creator has to sign for themselves.
+pub fn new_creators(
+    mut creators: Vec<Creator>,
+    signer: &Pubkey,
+) -> std::result::Result<Vec<Creator>, CreatorError> {
+    validate_creators(&creators)?;
+    for creator in creators.iter_mut() {
+        creator.verified = creator.address == *signer;
+    }
+    Ok(creators)
+}
+
+/// Marks `signer` as verified in `creators`
+pub fn verify_creator(
+    creators: &mut [Creator],
+    signer: &Pubkey,
+) -> std::result::Result<(), CreatorError> {
+    let creator = creators
+        .iter_mut()
+        .find(|c| c.address == *signer)
+        .ok_or(CreatorError::UnknownCreator)?;
+    creator.verified = true;
+    Ok(())
+}
+
+/// Encodes creators as the value of a `creators` TLV entry, `CREATOR_LEN` bytes each
+pub fn encode_creators(
+    creators: &[Creator],
+    out: &mut [u8],
+) -> std::result::Result<usize, TlvError> {
+    let len = creators.len() * CREATOR_LEN;
+    if len > out.len() {
+        return Err(TlvError::BufferTooSmall);
+    }
+    for (creator, chunk) in creators.iter().zip(out.chunks_exact_mut(CREATOR_LEN)) {
+        chunk[..32].copy_from_slice(creator.address.as_ref());
+        chunk[32..34].copy_from_slice(&creator.share_bps.to_le_bytes());
+        chunk[34] = creator.verified as u8;
+    }
+    Ok(len)
+}
+
+/// Decodes the value of a `creators` TLV entry
+pub fn decode_creators(value: &[u8]) -> std::result::Result<Vec<Creator>, TlvError> {
+    let chunks = value.chunks_exact(CREATOR_LEN);
+    if !chunks.remainder().is_empty() {
+        return Err(TlvError::Truncated);
+    }
+    Ok(chunks
+        .map(|chunk| Creator {
+            address: Pubkey::new_from_array(chunk[..32].try_into().unwrap()),
+            share_bps: u16::from_le_bytes([chunk[32], chunk[33]]),
+            verified: chunk[34] != 0,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creator(share_bps: u16) -> Creator {
+        Creator {
+            address: Pubkey::new_unique(),
+            share_bps,
+            verified: true,
+        }
+    }
+
+    #[test]
+    fn validates_shares_and_addresses() {
+        assert_eq!(validate_creators(&[]), Ok(()));
+        assert_eq!(validate_creators(&[creator(7_000), creator(3_000)]), Ok(()));
+        assert_eq!(
+            validate_creators(&[creator(7_000), creator(2_999)]),
+            Err(CreatorError::InvalidShares)
+        );
+        assert_eq!(
+            validate_creators(&[creator(u16::MAX), creator(u16::MAX)]),
+            Err(CreatorError::InvalidShares)
+        );
+        assert_eq!(
+            validate_creators(&[creator(2_000); MAX_CREATORS + 1]),
+            Err(CreatorError::TooManyCreators)
+        );
+
+        let duplicate = creator(5_000);
+        assert_eq!(
+            validate_creators(&[duplicate, duplicate]),
+            Err(CreatorError::DuplicateCreator)
+        );
+    }
+
+    #[test]
+    fn only_signers_are_verified() {
+        let (first, second) = (creator(5_000), creator(5_000));
+        let mut creators = new_creators(vec![first, second], &first.address).unwrap();
+        assert!(creators[0].verified);
+        assert!(!creators[1].verified);
+
+        assert_eq!(
+            verify_creator(&mut creators, &Pubkey::new_unique()),
+            Err(CreatorError::UnknownCreator)
+        );
+        verify_creator(&mut creators, &second.address).unwrap();
+        assert!(creators[1].verified);
+    }
+
+    #[test]
+    fn creators_round_trip() {
+        let creators = [
+            creator(4_000),
+            Creator {
+                verified: false,
+                ..creator(6_000)
+            },
+        ];
+        let mut out = [0u8; MAX_CREATORS * CREATOR_LEN];
+        let len = encode_creators(&creators, &mut out).unwrap();
+        assert_eq!(len, 2 * CREATOR_LEN);
+        assert_eq!(decode_creators(&out[..len]).unwrap(), creators);
+        assert_eq!(decode_creators(&out[..len - 1]), Err(TlvError::Truncated));
+    }
+
+    #[test]
+    fn serializes_address_as_base58() {
+        let creator = creator(10_000);
+        assert_eq!(
+            serde_json::to_value(creator).unwrap(),
+            serde_json::json!({
+                "address": creator.address.to_string(),
+                "share_bps": 10_000,
+                "verified": true,
+            })
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::{decode_cpi_event, CudEvent, FLAG_LOG_EVENT};
+
+/// Prefix the runtime puts in front of `sol_log_data` output
+pub const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Where events are written.
+///
+/// `Cpi` is what `emit_cpi!` does: the event is a self-CPI that indexers can read
+/// from inner instructions, which survives log truncation but needs the
+/// `event_authority` and `program` accounts. `Log` is what `emit!` does: cheaper,
+/// needs no accounts, but logs can be truncated by the RPC.
+///
+/// Build one with `event_emitter!()`, which picks the mode from the calling program's
+/// `log-events` feature. In CPI mode it wraps a call to the program's own `emit_cpi!`,
+/// so the self-CPI is always the one Anchor builds.
+pub enum EventEmitter<'a> {
+    Cpi(Box<dyn Fn(EncodedEvent) -> Result<()> + 'a>),
+    Log,
+}
+
+impl<'a> EventEmitter<'a> {
+    pub fn emit<E: anchor_lang::Event>(&self, event: &E) -> std::result::Result<(), ProgramError> {
+        match self {
+            EventEmitter::Cpi(emit_cpi) => {
+                emit_cpi(EncodedEvent(anchor_lang::Event::data(event))).map_err(ProgramError::from)
+            }
+            EventEmitter::Log => {
+                anchor_lang::solana_program::log::sol_log_data(&[&anchor_lang::Event::data(event)]);
+                Ok(())
+            }
+        }
+    }
+
+    /// Header flag recording how the AssetGroup was emitted
+    pub fn mode_flag(&self) -> u8 {
+        match self {
+            EventEmitter::Cpi(_) => 0,
+            EventEmitter::Log => FLAG_LOG_EVENT,
+        }
+    }
+}
+
+/// An event already serialized with `Event::data`, discriminator included.
+///
+/// `emit_cpi!` takes a concrete event, so this is what lets one `emit_cpi!` call
+/// site in `event_emitter!()` emit events of any type. Its data is passed through as is.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct EncodedEvent(pub Vec<u8>);
+
+impl anchor_lang::Discriminator for EncodedEvent {
+    // Never written: `data` already starts with the wrapped event's discriminator
+    const DISCRIMINATOR: [u8; 8] = [0; 8];
+}
+
+impl anchor_lang::Event for EncodedEvent {
+    fn data(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+}
+
+/// Decodes a `Program data: <base64>` log line written by `EventEmitter::Log`.
+///
+/// The caller is responsible for only passing lines logged by the program being indexed.
+/// Lines that are not program data decode to an empty vec.
+pub fn decode_program_data_log(log: &str) -> std::result::Result<Vec<CudEvent>, ProgramError> {
+    let encoded = match log.strip_prefix(PROGRAM_DATA_LOG_PREFIX) {
+        Some(encoded) => encoded,
+        None => return Ok(vec![]),
+    };
+    let data = anchor_lang::__private::base64::decode(encoded)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    decode_cpi_event(&data)
+}
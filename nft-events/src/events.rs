@@ -0,0 +1,206 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::AssetGroup;
+
+/// Many `CudCreate`s in one event, so bulk mints pay for a single self-CPI
+#[event]
+pub struct CudCreateBatch {
+    pub asset_groups: Vec<AssetGroup>,
+}
+
+/// Many `CudUpdate`s in one event
+#[event]
+pub struct CudUpdateBatch {
+    pub asset_groups: Vec<AssetGroup>,
+}
+
+/// A single sRFC16 CUD operation, with batches already expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CudEvent {
+    Create(AssetGroup),
+    Update(AssetGroup),
+    Delete { asset_id: Pubkey },
+}
+
+fn event_discriminator(name: &str) -> [u8; 8] {
+    let hash = anchor_lang::solana_program::hash::hash(format!("event:{}", name).as_bytes());
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&hash.to_bytes()[..8]);
+    disc
+}
+
+/// Decodes the instruction data of a CPI event into CUD operations.
+///
+/// Accepts data with or without the leading `EVENT_IX_TAG_LE`. `CudCreate`, `CudUpdate`
+/// and `CudDelete` are declared by each program but share a layout with `AssetGroup`,
+/// so they are matched by name. Batches expand into one operation per AssetGroup.
+/// Events that are not CUD operations decode to an empty vec.
+pub fn decode_cpi_event(ix_data: &[u8]) -> std::result::Result<Vec<CudEvent>, ProgramError> {
+    let data = ix_data
+        .strip_prefix(&anchor_lang::event::EVENT_IX_TAG_LE[..])
+        .unwrap_or(ix_data);
+    if data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (disc, mut body) = data.split_at(8);
+
+    let events = if disc == event_discriminator("CudCreate") {
+        vec![CudEvent::Create(AssetGroup::deserialize(&mut body)?)]
+    } else if disc == event_discriminator("CudUpdate") {
+        vec![CudEvent::Update(AssetGroup::deserialize(&mut body)?)]
+    } else if disc == event_discriminator("CudDelete") {
+        vec![CudEvent::Delete {
+            asset_id: Pubkey::deserialize(&mut body)?,
+        }]
+    } else if disc == CudCreateBatch::DISCRIMINATOR {
+        CudCreateBatch::deserialize(&mut body)?
+            .asset_groups
+            .into_iter()
+            .map(CudEvent::Create)
+            .collect()
+    } else if disc == CudUpdateBatch::DISCRIMINATOR {
+        CudUpdateBatch::deserialize(&mut body)?
+            .asset_groups
+            .into_iter()
+            .map(CudEvent::Update)
+            .collect()
+    } else {
+        vec![]
+    };
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::event::EVENT_IX_TAG_LE;
+    use anchor_lang::Event;
+
+    // The CUD events as each program declares them
+    #[event]
+    struct CudCreate {
+        asset_id: Pubkey,
+        authority: Pubkey,
+        pubkeys: Vec<Pubkey>,
+        data: Vec<u8>,
+    }
+
+    #[event]
+    struct CudUpdate {
+        asset_id: Pubkey,
+        authority: Pubkey,
+        pubkeys: Vec<Pubkey>,
+        data: Vec<u8>,
+    }
+
+    #[event]
+    struct CudDelete {
+        asset_id: Pubkey,
+    }
+
+    #[event]
+    struct Unrelated {
+        value: u64,
+    }
+
+    fn asset_group(i: u8) -> AssetGroup {
+        AssetGroup {
+            asset_id: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            pubkeys: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            data: vec![i; i as usize],
+        }
+    }
+
+    fn cpi_data(event: &impl Event) -> Vec<u8> {
+        let mut data = EVENT_IX_TAG_LE.to_vec();
+        data.extend_from_slice(&event.data());
+        data
+    }
+
+    #[test]
+    fn decodes_single_events() {
+        let group = asset_group(1);
+        let create = CudCreate {
+            asset_id: group.asset_id,
+            authority: group.authority,
+            pubkeys: group.pubkeys.clone(),
+            data: group.data.clone(),
+        };
+        assert_eq!(
+            decode_cpi_event(&cpi_data(&create)),
+            Ok(vec![CudEvent::Create(group.clone())])
+        );
+        // The tag is optional
+        assert_eq!(
+            decode_cpi_event(&create.data()),
+            Ok(vec![CudEvent::Create(group.clone())])
+        );
+
+        let update = CudUpdate {
+            asset_id: group.asset_id,
+            authority: group.authority,
+            pubkeys: group.pubkeys.clone(),
+            data: group.data.clone(),
+        };
+        assert_eq!(
+            decode_cpi_event(&cpi_data(&update)),
+            Ok(vec![CudEvent::Update(group.clone())])
+        );
+
+        let delete = CudDelete {
+            asset_id: group.asset_id,
+        };
+        assert_eq!(
+            decode_cpi_event(&cpi_data(&delete)),
+            Ok(vec![CudEvent::Delete {
+                asset_id: group.asset_id
+            }])
+        );
+
+        assert_eq!(
+            decode_cpi_event(&cpi_data(&Unrelated { value: 7 })),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn expands_batches_in_order() {
+        let groups: Vec<_> = (0..3).map(asset_group).collect();
+
+        let create = CudCreateBatch {
+            asset_groups: groups.clone(),
+        };
+        assert_eq!(
+            decode_cpi_event(&cpi_data(&create)),
+            Ok(groups.iter().cloned().map(CudEvent::Create).collect())
+        );
+
+        let update = CudUpdateBatch {
+            asset_groups: groups.clone(),
+        };
+        assert_eq!(
+            decode_cpi_event(&cpi_data(&update)),
+            Ok(groups.iter().cloned().map(CudEvent::Update).collect())
+        );
+
+        let empty = CudCreateBatch {
+            asset_groups: vec![],
+        };
+        assert_eq!(decode_cpi_event(&cpi_data(&empty)), Ok(vec![]));
+    }
+
+    #[test]
+    fn rejects_malformed_events() {
+        assert_eq!(
+            decode_cpi_event(&EVENT_IX_TAG_LE),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        let data = cpi_data(&CudCreateBatch {
+            asset_groups: vec![asset_group(4)],
+        });
+        assert!(decode_cpi_event(&data[..data.len() - 1]).is_err());
+    }
+}
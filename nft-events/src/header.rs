@@ -0,0 +1,203 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    asset_group_kind, get_collection_discriminator, get_metadata_discriminator, AssetGroupKind,
+};
+
+/// No header: `discriminator | payload`, as emitted before spec versioning existed
+pub const SPEC_VERSION_V0: u8 = 0;
+/// `discriminator | version | flags | payload`
+pub const SPEC_VERSION_V1: u8 = 1;
+pub const CURRENT_SPEC_VERSION: u8 = SPEC_VERSION_V1;
+
+/// Set on the version byte so that a header is never mistaken for a v0 payload.
+///
+/// v0 payloads are either empty or TLV maps, whose first byte is a key length of at
+/// most `MAX_KEY_LEN`. That bound is what keeps the two layouts apart, so it is checked
+/// at compile time here, and `encode_asset_data` refuses v0 payloads that would read
+/// back as a header.
+pub const SPEC_VERSION_TAG: u8 = 0x80;
+
+const _: () = assert!(crate::tlv::MAX_KEY_LEN < SPEC_VERSION_TAG as usize);
+
+/// The payload after the header is a `tlv` map
+pub const FLAG_TLV_PAYLOAD: u8 = 1 << 0;
+/// The event was written to the program logs with `sol_log_data` instead of a self-CPI
+pub const FLAG_LOG_EVENT: u8 = 1 << 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetDataError {
+    /// `data` does not start with an sRFC19 discriminator
+    UnknownDiscriminator,
+    /// The header names a version this crate cannot decode
+    UnsupportedVersion(u8),
+    /// The header is cut short
+    Truncated,
+}
+
+impl From<AssetDataError> for ProgramError {
+    fn from(_: AssetDataError) -> Self {
+        ProgramError::InvalidAccountData
+    }
+}
+
+impl From<AssetDataError> for anchor_lang::error::Error {
+    fn from(e: AssetDataError) -> Self {
+        ProgramError::from(e).into()
+    }
+}
+
+/// AssetGroup data split into its discriminator kind, header and payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedAssetData<'a> {
+    pub kind: AssetGroupKind,
+    pub version: u8,
+    pub flags: u8,
+    pub payload: &'a [u8],
+}
+
+impl<'a> DecodedAssetData<'a> {
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag == flag
+    }
+}
+
+/// Builds AssetGroup data for the given kind and spec version.
+///
+/// The first 8 bytes are always the `srfc19:collection`/`srfc19:metadata`
+/// discriminator, so indexers that key on them keep working across versions.
+/// v0 has nowhere to store flags, so passing any is an error, as is a v0 payload
+/// starting with a byte that has `SPEC_VERSION_TAG` set.
+pub fn encode_asset_data(
+    kind: AssetGroupKind,
+    version: u8,
+    flags: u8,
+    payload: &[u8],
+) -> std::result::Result<Vec<u8>, ProgramError> {
+    let mut data = match kind {
+        AssetGroupKind::Collection => get_collection_discriminator(),
+        AssetGroupKind::Metadata => get_metadata_discriminator(),
+    }
+    .map_err(ProgramError::from)?;
+    match version {
+        SPEC_VERSION_V0 => {
+            let ambiguous = matches!(payload.first(), Some(b) if b & SPEC_VERSION_TAG != 0);
+            if flags != 0 || ambiguous {
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+        SPEC_VERSION_V1 => {
+            data.push(SPEC_VERSION_TAG | version);
+            data.push(flags);
+        }
+        _ => return Err(ProgramError::InvalidArgument),
+    }
+    data.extend_from_slice(payload);
+    Ok(data)
+}
+
+/// Parses AssetGroup data written by any supported spec version
+pub fn decode_asset_data(data: &[u8]) -> std::result::Result<DecodedAssetData<'_>, AssetDataError> {
+    let kind = asset_group_kind(data).ok_or(AssetDataError::UnknownDiscriminator)?;
+    let body = &data[8..];
+
+    match body.first() {
+        Some(tag) if tag & SPEC_VERSION_TAG != 0 => {
+            let version = tag & !SPEC_VERSION_TAG;
+            if version != SPEC_VERSION_V1 {
+                return Err(AssetDataError::UnsupportedVersion(version));
+            }
+            let flags = *body.get(1).ok_or(AssetDataError::Truncated)?;
+            Ok(DecodedAssetData {
+                kind,
+                version,
+                flags,
+                payload: &body[2..],
+            })
+        }
+        _ => Ok(DecodedAssetData {
+            kind,
+            version: SPEC_VERSION_V0,
+            flags: 0,
+            payload: body,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tlv::encode_tlv;
+
+    #[test]
+    fn round_trips_each_version() {
+        let payload = encode_tlv(&[("name", b"name")]).unwrap();
+        for kind in [AssetGroupKind::Collection, AssetGroupKind::Metadata] {
+            let v0 = encode_asset_data(kind, SPEC_VERSION_V0, 0, &payload).unwrap();
+            assert_eq!(v0.len(), 8 + payload.len());
+            assert_eq!(
+                decode_asset_data(&v0),
+                Ok(DecodedAssetData {
+                    kind,
+                    version: SPEC_VERSION_V0,
+                    flags: 0,
+                    payload: &payload,
+                })
+            );
+
+            let flags = FLAG_TLV_PAYLOAD | FLAG_LOG_EVENT;
+            let v1 = encode_asset_data(kind, SPEC_VERSION_V1, flags, &payload).unwrap();
+            assert_eq!(v1[..8], v0[..8]);
+            assert_eq!(v1[8..10], [0x81, 3]);
+            let decoded = decode_asset_data(&v1).unwrap();
+            assert_eq!(decoded.version, SPEC_VERSION_V1);
+            assert!(decoded.has_flag(FLAG_TLV_PAYLOAD));
+            assert!(decoded.has_flag(FLAG_LOG_EVENT));
+            assert_eq!(decoded.payload, &payload[..]);
+
+            let empty = encode_asset_data(kind, SPEC_VERSION_V0, 0, &[]).unwrap();
+            assert_eq!(decode_asset_data(&empty).unwrap().payload, &[] as &[u8]);
+        }
+    }
+
+    #[test]
+    fn rejects_ambiguous_or_unknown_headers() {
+        let kind = AssetGroupKind::Metadata;
+        assert_eq!(
+            encode_asset_data(kind, SPEC_VERSION_V0, FLAG_TLV_PAYLOAD, &[]),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            encode_asset_data(kind, SPEC_VERSION_V0, 0, &[0x81, 0]),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            encode_asset_data(kind, 2, 0, &[]),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut data = encode_asset_data(kind, SPEC_VERSION_V1, 0, &[]).unwrap();
+        data[8] = SPEC_VERSION_TAG | 2;
+        assert_eq!(
+            decode_asset_data(&data),
+            Err(AssetDataError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            decode_asset_data(&data[..9]),
+            Err(AssetDataError::UnsupportedVersion(2))
+        );
+        data[8] = SPEC_VERSION_TAG | SPEC_VERSION_V1;
+        assert_eq!(
+            decode_asset_data(&data[..9]),
+            Err(AssetDataError::Truncated)
+        );
+        assert_eq!(
+            decode_asset_data(&[0; 10]),
+            Err(AssetDataError::UnknownDiscriminator)
+        );
+        assert_eq!(
+            decode_asset_data(&data[..7]),
+            Err(AssetDataError::UnknownDiscriminator)
+        );
+    }
+}
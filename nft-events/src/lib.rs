@@ -2,6 +2,29 @@ use anchor_lang::prelude::*;
 
 pub use nft_events_macros::*;
 
+mod creators;
+mod emitter;
+mod events;
+mod header;
+mod render;
+pub mod tlv;
+mod validation;
+pub use creators::*;
+pub use emitter::*;
+pub use events::*;
+pub use header::*;
+pub use render::*;
+pub use validation::*;
+
+/// An sRFC16 AssetGroup, as carried by `CudCreate` and `CudUpdate`
+#[derive(Debug, Clone, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct AssetGroup {
+    pub asset_id: Pubkey,
+    pub authority: Pubkey,
+    pub pubkeys: Vec<Pubkey>,
+    pub data: Vec<u8>,
+}
+
 pub struct NftCollectionAsset {
     pub asset_id: Pubkey,
     pub authority: Pubkey,
@@ -18,6 +41,23 @@ pub struct NftMetadataAsset {
     pub data: Vec<u8>,
 }
 
+/// Provenance for a single ownership change, emitted as `NftTransferred`
+pub struct NftTransfer {
+    pub asset: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    /// Signer that authorized the transfer, either the owner or the delegate
+    pub authority: Pubkey,
+}
+
+/// A delegate being set or cleared, emitted as `NftDelegated` or `NftRevoked`
+pub struct NftDelegation {
+    pub asset: Pubkey,
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub authority: Pubkey,
+}
+
 pub fn get_collection_discriminator() -> Result<Vec<u8>> {
     let disc = anchor_lang::solana_program::hash::hash(b"srfc19:collection")
         .try_to_vec()
@@ -33,3 +73,36 @@ pub fn get_metadata_discriminator() -> Result<Vec<u8>> {
     let data = disc[0..8].to_vec();
     return Ok(data);
 }
+
+pub fn get_tlv_render_discriminator() -> [u8; 8] {
+    let hash = anchor_lang::solana_program::hash::hash(b"srfc19:render:tlv");
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&hash.to_bytes()[..8]);
+    disc
+}
+
+pub fn get_page_render_discriminator() -> [u8; 8] {
+    let hash = anchor_lang::solana_program::hash::hash(b"srfc19:render:page");
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&hash.to_bytes()[..8]);
+    disc
+}
+
+/// Output format requested from `getAssetData`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderFormat {
+    Json,
+    Tlv,
+}
+
+impl RenderFormat {
+    /// Clients opt into TLV by prefixing the `getAssetData` input with the
+    /// `srfc19:render:tlv` discriminator. Anything else renders JSON.
+    pub fn from_request(data: &[u8]) -> Self {
+        if data.starts_with(&get_tlv_render_discriminator()) {
+            RenderFormat::Tlv
+        } else {
+            RenderFormat::Json
+        }
+    }
+}
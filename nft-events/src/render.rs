@@ -0,0 +1,206 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::MAX_RETURN_DATA;
+
+use crate::{get_page_render_discriminator, get_tlv_render_discriminator, RenderFormat};
+
+/// Largest document a program renders for paged requests. Paged documents are rendered
+/// into a heap buffer of this size, since the BPF stack cannot hold them.
+pub const MAX_RENDERED_LEN: usize = 10 * 1024;
+
+/// `[total_len: u32 LE]` in front of every page
+pub const PAGE_HEADER_LEN: usize = 4;
+
+/// Most document bytes a single page can carry
+pub const MAX_PAGE_LEN: usize = MAX_RETURN_DATA - PAGE_HEADER_LEN;
+
+/// `[offset: u32 LE][length: u16 LE]` after the page discriminator
+const PAGE_REQUEST_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageError {
+    /// A page response is shorter than its header
+    Truncated,
+    /// A page does not continue the document where the previous one ended
+    Inconsistent,
+}
+
+impl From<PageError> for ProgramError {
+    fn from(_: PageError) -> Self {
+        ProgramError::InvalidAccountData
+    }
+}
+
+impl From<PageError> for anchor_lang::error::Error {
+    fn from(e: PageError) -> Self {
+        ProgramError::from(e).into()
+    }
+}
+
+/// A byte range of the rendered document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub offset: u32,
+    pub length: u16,
+}
+
+impl PageRequest {
+    /// Encodes a `getAssetData` input for this page:
+    /// `[srfc19:render:page][offset: u32 LE][length: u16 LE]`, followed by the
+    /// `srfc19:render:tlv` discriminator when TLV is requested
+    pub fn to_request(&self, format: RenderFormat) -> Vec<u8> {
+        let mut data = get_page_render_discriminator().to_vec();
+        data.extend_from_slice(&self.offset.to_le_bytes());
+        data.extend_from_slice(&self.length.to_le_bytes());
+        if format == RenderFormat::Tlv {
+            data.extend_from_slice(&get_tlv_render_discriminator());
+        }
+        data
+    }
+
+    /// Builds the return data for this page of `document`: the total size of the document,
+    /// then up to `length` bytes from `offset`. Offsets past the end return an empty page.
+    pub fn response(&self, document: &[u8]) -> Vec<u8> {
+        let start = (self.offset as usize).min(document.len());
+        let len = (self.length as usize).min(MAX_PAGE_LEN);
+        let end = start.saturating_add(len).min(document.len());
+
+        let mut data = Vec::with_capacity(PAGE_HEADER_LEN + end - start);
+        data.extend_from_slice(&(document.len() as u32).to_le_bytes());
+        data.extend_from_slice(&document[start..end]);
+        data
+    }
+}
+
+/// A parsed `getAssetData` input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderRequest {
+    pub format: RenderFormat,
+    /// `None` returns the whole document, which must then fit in `MAX_RETURN_DATA`
+    pub page: Option<PageRequest>,
+}
+
+impl RenderRequest {
+    /// Paged requests start with the `srfc19:render:page` discriminator, offset and length,
+    /// followed by an ordinary request that selects the format. Anything else is unpaged.
+    pub fn parse(data: &[u8]) -> Self {
+        let paged = data
+            .strip_prefix(&get_page_render_discriminator()[..])
+            .filter(|rest| rest.len() >= PAGE_REQUEST_LEN);
+
+        match paged {
+            Some(rest) => RenderRequest {
+                format: RenderFormat::from_request(&rest[PAGE_REQUEST_LEN..]),
+                page: Some(PageRequest {
+                    offset: u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]),
+                    length: u16::from_le_bytes([rest[4], rest[5]]),
+                }),
+            },
+            None => RenderRequest {
+                format: RenderFormat::from_request(data),
+                page: None,
+            },
+        }
+    }
+}
+
+/// Splits a page response into the document's total size and the page's bytes
+pub fn decode_page(data: &[u8]) -> std::result::Result<(usize, &[u8]), PageError> {
+    if data.len() < PAGE_HEADER_LEN {
+        return Err(PageError::Truncated);
+    }
+    let total = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    Ok((total, &data[PAGE_HEADER_LEN..]))
+}
+
+/// Client helper that pages through a whole rendered document.
+///
+/// `fetch` receives each encoded `getAssetData` input and returns the program's return
+/// data, usually by simulating the instruction. Pages are requested until the total size
+/// reported in the first page has been read.
+pub fn fetch_paged_asset_data<F, E>(
+    format: RenderFormat,
+    mut fetch: F,
+) -> std::result::Result<Vec<u8>, E>
+where
+    F: FnMut(Vec<u8>) -> std::result::Result<Vec<u8>, E>,
+    E: From<PageError>,
+{
+    let mut document = Vec::new();
+    loop {
+        let request = PageRequest {
+            offset: document.len() as u32,
+            length: MAX_PAGE_LEN as u16,
+        }
+        .to_request(format);
+        let response = fetch(request)?;
+        let (total, page) = decode_page(&response)?;
+
+        if document.len() + page.len() > total {
+            return Err(PageError::Inconsistent.into());
+        }
+        document.extend_from_slice(page);
+        if document.len() == total {
+            return Ok(document);
+        }
+        if page.is_empty() {
+            return Err(PageError::Inconsistent.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_paged_and_unpaged_requests() {
+        let page = PageRequest {
+            offset: 2048,
+            length: 512,
+        };
+        for format in [RenderFormat::Json, RenderFormat::Tlv] {
+            assert_eq!(
+                RenderRequest::parse(&page.to_request(format)),
+                RenderRequest {
+                    format,
+                    page: Some(page),
+                }
+            );
+        }
+
+        let tlv = get_tlv_render_discriminator();
+        assert_eq!(
+            RenderRequest::parse(&tlv),
+            RenderRequest {
+                format: RenderFormat::Tlv,
+                page: None,
+            }
+        );
+        assert_eq!(RenderRequest::parse(&[]).page, None);
+    }
+
+    #[test]
+    fn reassembles_pages() {
+        let document: Vec<u8> = (0..3000u32).map(|i| i as u8).collect();
+
+        let mut requests = 0;
+        let fetched = fetch_paged_asset_data::<_, PageError>(RenderFormat::Json, |request| {
+            requests += 1;
+            let request = RenderRequest::parse(&request);
+            Ok(request.page.unwrap().response(&document))
+        })
+        .unwrap();
+
+        assert_eq!(fetched, document);
+        assert_eq!(requests, 3);
+    }
+
+    #[test]
+    fn past_the_end_is_empty() {
+        let page = PageRequest {
+            offset: 100,
+            length: 10,
+        };
+        assert_eq!(page.response(&[1, 2, 3]), 3u32.to_le_bytes().to_vec());
+    }
+}
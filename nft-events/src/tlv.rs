@@ -0,0 +1,370 @@
+//! TLV<string, bytes> maps for AssetGroup data and `getAssetData` output.
+//!
+//! Rendering JSON on-chain is expensive, so sRFC19 assets can instead encode
+//! their metadata as a flat list of entries:
+//!
+//! ```text
+//! [key_len: u8][key: utf8][value_len: u16 LE][value]
+//! ```
+//!
+//! Well-known string fields hold utf8 bytes, and pubkey fields (`owner`, `delegate`,
+//! `authority`) hold the raw 32 byte key. Attributes are one utf8 entry per trait,
+//! keyed `attr:<trait_type>`, and `creators` holds `CREATOR_LEN` bytes per creator.
+use anchor_lang::prelude::*;
+
+use crate::{encode_creators, Creator, CREATOR_LEN, MAX_CREATORS};
+
+pub const NAME: &str = "name";
+pub const SYMBOL: &str = "symbol";
+pub const URI: &str = "uri";
+pub const OWNER: &str = "owner";
+pub const DELEGATE: &str = "delegate";
+pub const AUTHORITY: &str = "authority";
+pub const CREATORS: &str = "creators";
+
+/// Prefix of attribute keys, followed by the trait type
+pub const ATTRIBUTE_PREFIX: &str = "attr:";
+
+/// Keys with a registered meaning across all sRFC19 programs
+pub const WELL_KNOWN_KEYS: [&str; 7] = [NAME, SYMBOL, URI, OWNER, DELEGATE, AUTHORITY, CREATORS];
+
+/// Keys stay below 128 bytes so a TLV payload never starts with the spec version tag
+pub const MAX_KEY_LEN: usize = 127;
+
+const KEY_LEN_SIZE: usize = 1;
+const VALUE_LEN_SIZE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlvError {
+    /// The writer's buffer cannot hold the next entry
+    BufferTooSmall,
+    /// Key is longer than `MAX_KEY_LEN` bytes or value is longer than 65535 bytes
+    EntryTooLarge,
+    /// An entry's declared length runs past the end of the data
+    Truncated,
+    /// A key is not valid utf8
+    InvalidKey,
+}
+
+impl From<TlvError> for ProgramError {
+    fn from(e: TlvError) -> Self {
+        match e {
+            TlvError::BufferTooSmall => ProgramError::AccountDataTooSmall,
+            TlvError::EntryTooLarge => ProgramError::InvalidArgument,
+            TlvError::Truncated | TlvError::InvalidKey => ProgramError::InvalidAccountData,
+        }
+    }
+}
+
+impl From<TlvError> for anchor_lang::error::Error {
+    fn from(e: TlvError) -> Self {
+        ProgramError::from(e).into()
+    }
+}
+
+/// Number of bytes a single entry takes once encoded
+pub fn entry_len(key: &str, value: &[u8]) -> usize {
+    KEY_LEN_SIZE + key.len() + VALUE_LEN_SIZE + value.len()
+}
+
+/// Number of bytes an attribute entry takes once encoded
+pub fn attribute_entry_len(trait_type: &str, value: &str) -> usize {
+    entry_len(ATTRIBUTE_PREFIX, value.as_bytes()) + trait_type.len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlvEntry<'a> {
+    pub key: &'a str,
+    pub value: &'a [u8],
+}
+
+impl<'a> TlvEntry<'a> {
+    pub fn value_str(&self) -> Option<&'a str> {
+        core::str::from_utf8(self.value).ok()
+    }
+
+    pub fn value_pubkey(&self) -> Option<Pubkey> {
+        <[u8; 32]>::try_from(self.value).ok().map(Pubkey::from)
+    }
+
+    /// The trait type, if this entry is an attribute
+    pub fn attribute_trait(&self) -> Option<&'a str> {
+        self.key.strip_prefix(ATTRIBUTE_PREFIX)
+    }
+}
+
+/// Zero-copy reader over TLV encoded bytes. Iterating yields entries that borrow from the input.
+#[derive(Debug, Clone, Copy)]
+pub struct TlvReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> TlvReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    /// Returns the first entry with the given key
+    pub fn get(&self, key: &str) -> std::result::Result<Option<TlvEntry<'a>>, TlvError> {
+        for entry in TlvReader::new(self.data) {
+            let entry = entry?;
+            if entry.key == key {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn get_str(&self, key: &str) -> std::result::Result<Option<&'a str>, TlvError> {
+        Ok(self.get(key)?.and_then(|entry| entry.value_str()))
+    }
+
+    pub fn get_pubkey(&self, key: &str) -> std::result::Result<Option<Pubkey>, TlvError> {
+        Ok(self.get(key)?.and_then(|entry| entry.value_pubkey()))
+    }
+
+    fn read_entry(&mut self) -> std::result::Result<TlvEntry<'a>, TlvError> {
+        let data = self.data;
+        let mut cursor = self.offset;
+
+        let key_len = *data.get(cursor).ok_or(TlvError::Truncated)? as usize;
+        cursor += KEY_LEN_SIZE;
+        let key = data
+            .get(cursor..cursor + key_len)
+            .ok_or(TlvError::Truncated)?;
+        let key = core::str::from_utf8(key).map_err(|_| TlvError::InvalidKey)?;
+        cursor += key_len;
+
+        let value_len = data
+            .get(cursor..cursor + VALUE_LEN_SIZE)
+            .ok_or(TlvError::Truncated)?;
+        let value_len = u16::from_le_bytes([value_len[0], value_len[1]]) as usize;
+        cursor += VALUE_LEN_SIZE;
+        let value = data
+            .get(cursor..cursor + value_len)
+            .ok_or(TlvError::Truncated)?;
+        cursor += value_len;
+
+        self.offset = cursor;
+        Ok(TlvEntry { key, value })
+    }
+}
+
+impl<'a> Iterator for TlvReader<'a> {
+    type Item = std::result::Result<TlvEntry<'a>, TlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.data.len() {
+            return None;
+        }
+        let entry = self.read_entry();
+        if entry.is_err() {
+            // Stop iterating after the first malformed entry
+            self.offset = self.data.len();
+        }
+        Some(entry)
+    }
+}
+
+/// Writes TLV entries into a caller-provided buffer, so it can target a
+/// fixed `[u8; MAX_RETURN_DATA]` on-chain without allocating.
+pub struct TlvWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> TlvWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    pub fn write(&mut self, key: &str, value: &[u8]) -> std::result::Result<(), TlvError> {
+        if key.len() > MAX_KEY_LEN || value.len() > u16::MAX as usize {
+            return Err(TlvError::EntryTooLarge);
+        }
+        let end = self.len + entry_len(key, value);
+        if end > self.buf.len() {
+            return Err(TlvError::BufferTooSmall);
+        }
+
+        let mut cursor = self.len;
+        self.buf[cursor] = key.len() as u8;
+        cursor += KEY_LEN_SIZE;
+        self.buf[cursor..cursor + key.len()].copy_from_slice(key.as_bytes());
+        cursor += key.len();
+        self.buf[cursor..cursor + VALUE_LEN_SIZE]
+            .copy_from_slice(&(value.len() as u16).to_le_bytes());
+        cursor += VALUE_LEN_SIZE;
+        self.buf[cursor..end].copy_from_slice(value);
+
+        self.len = end;
+        Ok(())
+    }
+
+    pub fn write_str(&mut self, key: &str, value: &str) -> std::result::Result<(), TlvError> {
+        self.write(key, value.as_bytes())
+    }
+
+    pub fn write_pubkey(&mut self, key: &str, value: &Pubkey) -> std::result::Result<(), TlvError> {
+        self.write(key, value.as_ref())
+    }
+
+    /// Writes a `creators` entry, encoded on the stack
+    pub fn write_creators(&mut self, creators: &[Creator]) -> std::result::Result<(), TlvError> {
+        let mut value = [0u8; MAX_CREATORS * CREATOR_LEN];
+        let len = encode_creators(creators, &mut value).map_err(|_| TlvError::EntryTooLarge)?;
+        self.write(CREATORS, &value[..len])
+    }
+
+    /// Writes an `attr:<trait_type>` entry, building the key on the stack
+    pub fn write_attribute(
+        &mut self,
+        trait_type: &str,
+        value: &str,
+    ) -> std::result::Result<(), TlvError> {
+        let key_len = ATTRIBUTE_PREFIX.len() + trait_type.len();
+        if key_len > MAX_KEY_LEN {
+            return Err(TlvError::EntryTooLarge);
+        }
+        let mut key = [0u8; MAX_KEY_LEN];
+        key[..ATTRIBUTE_PREFIX.len()].copy_from_slice(ATTRIBUTE_PREFIX.as_bytes());
+        key[ATTRIBUTE_PREFIX.len()..key_len].copy_from_slice(trait_type.as_bytes());
+        let key = core::str::from_utf8(&key[..key_len]).map_err(|_| TlvError::InvalidKey)?;
+        self.write(key, value.as_bytes())
+    }
+
+    /// Number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The encoded bytes written so far
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// Encodes `entries` into an exactly-sized `Vec`, for event payloads
+pub fn encode_tlv(entries: &[(&str, &[u8])]) -> std::result::Result<Vec<u8>, TlvError> {
+    let len = entries
+        .iter()
+        .map(|(key, value)| entry_len(key, value))
+        .sum();
+    let mut buf = vec![0u8; len];
+    let mut writer = TlvWriter::new(&mut buf);
+    for (key, value) in entries {
+        writer.write(key, value)?;
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_entries() {
+        let owner = Pubkey::new_unique();
+        let mut buf = [0u8; 256];
+        let mut writer = TlvWriter::new(&mut buf);
+        writer.write_str(NAME, "name").unwrap();
+        writer.write_pubkey(OWNER, &owner).unwrap();
+        writer.write_attribute("eyes", "laser").unwrap();
+        writer.write(URI, &[]).unwrap();
+        let len = writer.len();
+        assert_eq!(
+            len,
+            entry_len(NAME, b"name")
+                + entry_len(OWNER, owner.as_ref())
+                + attribute_entry_len("eyes", "laser")
+                + entry_len(URI, &[])
+        );
+        assert_eq!(&buf[..7], b"\x04name\x04\x00");
+
+        let reader = TlvReader::new(&buf[..len]);
+        assert_eq!(reader.get_str(NAME), Ok(Some("name")));
+        assert_eq!(reader.get_pubkey(OWNER), Ok(Some(owner)));
+        assert_eq!(reader.get_str(URI), Ok(Some("")));
+        assert_eq!(reader.get(SYMBOL), Ok(None));
+
+        let entries: Vec<_> = reader.map(|entry| entry.unwrap()).collect();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[2].key, "attr:eyes");
+        assert_eq!(entries[2].attribute_trait(), Some("eyes"));
+        assert_eq!(entries[2].value_str(), Some("laser"));
+        assert_eq!(entries[0].attribute_trait(), None);
+
+        assert_eq!(
+            encode_tlv(&[(NAME, b"name"), (OWNER, owner.as_ref())]).unwrap(),
+            buf[..entry_len(NAME, b"name") + entry_len(OWNER, owner.as_ref())]
+        );
+    }
+
+    #[test]
+    fn bounds_keys_and_values() {
+        let mut buf = vec![0u8; 70_000];
+        let mut writer = TlvWriter::new(&mut buf);
+
+        let key = "k".repeat(MAX_KEY_LEN);
+        writer.write(&key, b"v").unwrap();
+        assert_eq!(
+            writer.write(&"k".repeat(MAX_KEY_LEN + 1), b"v"),
+            Err(TlvError::EntryTooLarge)
+        );
+        assert_eq!(
+            writer.write_attribute(&"t".repeat(MAX_KEY_LEN), "v"),
+            Err(TlvError::EntryTooLarge)
+        );
+
+        writer.write(NAME, &vec![1; u16::MAX as usize]).unwrap();
+        assert_eq!(
+            writer.write(NAME, &vec![1; u16::MAX as usize + 1]),
+            Err(TlvError::EntryTooLarge)
+        );
+
+        let reader = TlvReader::new(writer.as_bytes());
+        assert_eq!(reader.get_str(&key), Ok(Some("v")));
+        assert_eq!(
+            reader.get(NAME).unwrap().unwrap().value.len(),
+            u16::MAX as usize
+        );
+
+        let mut small = [0u8; 8];
+        let mut writer = TlvWriter::new(&mut small);
+        assert_eq!(
+            writer.write_str(NAME, "name"),
+            Err(TlvError::BufferTooSmall)
+        );
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let entry = encode_tlv(&[(NAME, b"name")]).unwrap();
+        // Cut inside the key, the value length and the value
+        for len in [1, 3, 6, 8, entry.len() - 1] {
+            let mut reader = TlvReader::new(&entry[..len]);
+            assert_eq!(reader.next(), Some(Err(TlvError::Truncated)));
+            assert_eq!(reader.next(), None);
+        }
+        assert_eq!(
+            TlvReader::new(&entry[..5]).get(NAME),
+            Err(TlvError::Truncated)
+        );
+
+        // A valid entry followed by a malformed one stops after the error
+        let mut data = entry.clone();
+        data.extend_from_slice(&[2, 0xff, 0xfe, 0, 0]);
+        let mut reader = TlvReader::new(&data);
+        assert!(reader.next().unwrap().is_ok());
+        assert_eq!(reader.next(), Some(Err(TlvError::InvalidKey)));
+        assert_eq!(reader.next(), None);
+
+        assert_eq!(TlvReader::new(&[]).next(), None);
+    }
+}
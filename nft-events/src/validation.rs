@@ -0,0 +1,360 @@
+use anchor_lang::prelude::*;
+
+use crate::{get_collection_discriminator, get_metadata_discriminator, NftMetadataAsset};
+
+/// Fields that every rendered `getAssetData` JSON document must contain
+pub const REQUIRED_RENDERED_FIELDS: [&str; 5] = ["name", "symbol", "uri", "delegate", "owner"];
+
+/// Which kind of sRFC19 asset an AssetGroup describes, derived from its data discriminator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetGroupKind {
+    Collection,
+    Metadata,
+}
+
+/// A single way in which an AssetGroup (or its rendered JSON) breaks the sRFC19 grouping rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetGroupViolation {
+    /// `data` is shorter than the 8 byte sRFC19 discriminator
+    MissingDiscriminator,
+    /// `data` does not start with `srfc19:collection` or `srfc19:metadata`
+    UnknownDiscriminator,
+    /// `pubkeys[0]` is missing
+    MissingCollectionOrProgram,
+    /// `pubkeys[0]` is not the collection or program id
+    InvalidCollectionOrProgram { found: Pubkey },
+    /// `pubkeys[1]` is missing
+    MissingOwnerOrDelegate,
+    /// `pubkeys[1]` is not the current delegate or owner
+    InvalidOwnerOrDelegate { expected: Pubkey, found: Pubkey },
+    /// Rendered metadata is not a JSON object
+    InvalidRenderedJson,
+    /// Rendered metadata is missing a required field, or it is not a string
+    MissingRenderedField(&'static str),
+}
+
+/// What the validator knows about the asset besides the AssetGroup itself.
+///
+/// Every field but `program_id` is optional. An unknown `collection` lets metadata list
+/// any other key first, and when `owner` and `delegate` are both unknown the 2nd pubkey
+/// is only checked for presence. When the NFT is delegated, the delegate takes precedence
+/// over the owner.
+///
+/// Accounts that `#[derive(NftAsset)]` build this from their own fields with
+/// `asset_group_context()`.
+#[derive(Debug, Clone, Default)]
+pub struct AssetGroupContext {
+    pub program_id: Pubkey,
+    pub collection: Option<Pubkey>,
+    pub owner: Option<Pubkey>,
+    pub delegate: Option<Pubkey>,
+}
+
+impl AssetGroupContext {
+    pub fn expected_owner_or_delegate(&self) -> Option<Pubkey> {
+        self.delegate.or(self.owner)
+    }
+}
+
+/// Returns the sRFC19 asset kind encoded in the first 8 bytes of `data`
+pub fn asset_group_kind(data: &[u8]) -> Option<AssetGroupKind> {
+    if data.len() < 8 {
+        return None;
+    }
+    let disc = &data[0..8];
+    if disc == get_collection_discriminator().ok()?.as_slice() {
+        Some(AssetGroupKind::Collection)
+    } else if disc == get_metadata_discriminator().ok()?.as_slice() {
+        Some(AssetGroupKind::Metadata)
+    } else {
+        None
+    }
+}
+
+/// Checks an AssetGroup against the sRFC19 grouping rules.
+///
+/// Collections must list themselves (or the program id) first. Metadata must list
+/// its collection (or the program id) first, followed by its delegate or owner.
+/// Returns every violation found, so an empty vec means the group conforms.
+pub fn validate_asset_group(
+    asset_id: &Pubkey,
+    pubkeys: &[Pubkey],
+    data: &[u8],
+    ctx: &AssetGroupContext,
+) -> Vec<AssetGroupViolation> {
+    let mut violations = vec![];
+
+    let kind = if data.len() < 8 {
+        violations.push(AssetGroupViolation::MissingDiscriminator);
+        None
+    } else {
+        let kind = asset_group_kind(data);
+        if kind.is_none() {
+            violations.push(AssetGroupViolation::UnknownDiscriminator);
+        }
+        kind
+    };
+
+    match pubkeys.first() {
+        None => violations.push(AssetGroupViolation::MissingCollectionOrProgram),
+        Some(first) => {
+            let valid = match kind {
+                Some(AssetGroupKind::Collection) => first == asset_id || *first == ctx.program_id,
+                _ => match ctx.collection {
+                    Some(collection) => *first == collection || *first == ctx.program_id,
+                    None => first != asset_id && *first != Pubkey::default(),
+                },
+            };
+            if !valid {
+                violations.push(AssetGroupViolation::InvalidCollectionOrProgram { found: *first });
+            }
+        }
+    }
+
+    if kind != Some(AssetGroupKind::Collection) {
+        match pubkeys.get(1) {
+            None => violations.push(AssetGroupViolation::MissingOwnerOrDelegate),
+            Some(second) => {
+                if let Some(expected) = ctx.expected_owner_or_delegate() {
+                    if *second != expected {
+                        violations.push(AssetGroupViolation::InvalidOwnerOrDelegate {
+                            expected,
+                            found: *second,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    violations
+}
+
+/// Checks that rendered `getAssetData` output contains the sRFC19 required fields.
+///
+/// Meant for clients and tests, so it is only built with the `validate-rendered` feature
+/// and keeps `serde_json` out of programs.
+#[cfg(any(test, feature = "validate-rendered"))]
+pub fn validate_rendered_asset(json: &[u8]) -> Vec<AssetGroupViolation> {
+    let value: serde_json::Value = match serde_json::from_slice(json) {
+        Ok(value) => value,
+        Err(_) => return vec![AssetGroupViolation::InvalidRenderedJson],
+    };
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return vec![AssetGroupViolation::InvalidRenderedJson],
+    };
+
+    REQUIRED_RENDERED_FIELDS
+        .iter()
+        .filter(|field| !matches!(object.get(**field), Some(v) if v.is_string()))
+        .map(|field| AssetGroupViolation::MissingRenderedField(field))
+        .collect()
+}
+
+impl NftMetadataAsset {
+    /// The group's pubkeys (collection, delegate or owner, then `pubkeys`), checked with
+    /// `debug_assert_asset_group` against what the account itself stores.
+    pub fn checked_pubkeys(&self, data: &[u8], ctx: &AssetGroupContext) -> Vec<Pubkey> {
+        let mut pubkeys = vec![self.collection, self.delegate];
+        pubkeys.extend_from_slice(&self.pubkeys);
+        debug_assert_asset_group(&self.asset_id, &pubkeys, data, ctx);
+        pubkeys
+    }
+}
+
+/// Called by the `emit_*` macros before an AssetGroup is emitted.
+///
+/// Panics with the list of violations in debug builds, or whenever the
+/// `strict-spec` feature is enabled, so programs break loudly in tests.
+/// Compiles to nothing otherwise.
+#[allow(unused_variables)]
+pub fn debug_assert_asset_group(
+    asset_id: &Pubkey,
+    pubkeys: &[Pubkey],
+    data: &[u8],
+    ctx: &AssetGroupContext,
+) {
+    #[cfg(any(debug_assertions, feature = "strict-spec"))]
+    {
+        let violations = validate_asset_group(asset_id, pubkeys, data, ctx);
+        if !violations.is_empty() {
+            panic!("AssetGroup {} violates sRFC19: {:?}", asset_id, violations);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_asset_data, CURRENT_SPEC_VERSION};
+
+    fn data(kind: AssetGroupKind) -> Vec<u8> {
+        encode_asset_data(kind, CURRENT_SPEC_VERSION, 0, &[]).unwrap()
+    }
+
+    fn context(collection: Pubkey, owner: Pubkey, delegate: Option<Pubkey>) -> AssetGroupContext {
+        AssetGroupContext {
+            program_id: Pubkey::new_unique(),
+            collection: Some(collection),
+            owner: Some(owner),
+            delegate,
+        }
+    }
+
+    #[test]
+    fn accepts_conforming_groups() {
+        let (asset, collection, owner, delegate) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let metadata = data(AssetGroupKind::Metadata);
+
+        let ctx = context(collection, owner, None);
+        assert_eq!(
+            validate_asset_group(&asset, &[collection, owner], &metadata, &ctx),
+            vec![]
+        );
+        let ctx = context(collection, owner, Some(delegate));
+        assert_eq!(
+            validate_asset_group(&asset, &[collection, delegate, owner], &metadata, &ctx),
+            vec![]
+        );
+        // The program id can stand in for the collection
+        assert_eq!(
+            validate_asset_group(&asset, &[ctx.program_id, delegate], &metadata, &ctx),
+            vec![]
+        );
+
+        let ctx = AssetGroupContext::default();
+        assert_eq!(
+            validate_asset_group(
+                &collection,
+                &[collection],
+                &data(AssetGroupKind::Collection),
+                &ctx
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn rejects_misordered_groups() {
+        let (asset, collection, owner, delegate) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let metadata = data(AssetGroupKind::Metadata);
+        let ctx = context(collection, owner, Some(delegate));
+
+        // The owner is listed while the asset is delegated
+        assert_eq!(
+            validate_asset_group(&asset, &[collection, owner], &metadata, &ctx),
+            vec![AssetGroupViolation::InvalidOwnerOrDelegate {
+                expected: delegate,
+                found: owner,
+            }]
+        );
+        // Keys swapped, so neither slot holds what the account says it should
+        assert_eq!(
+            validate_asset_group(&asset, &[delegate, collection], &metadata, &ctx),
+            vec![
+                AssetGroupViolation::InvalidCollectionOrProgram { found: delegate },
+                AssetGroupViolation::InvalidOwnerOrDelegate {
+                    expected: delegate,
+                    found: collection,
+                },
+            ]
+        );
+        assert_eq!(
+            validate_asset_group(&asset, &[asset], &metadata, &AssetGroupContext::default()),
+            vec![
+                AssetGroupViolation::InvalidCollectionOrProgram { found: asset },
+                AssetGroupViolation::MissingOwnerOrDelegate,
+            ]
+        );
+        assert_eq!(
+            validate_asset_group(
+                &asset,
+                &[],
+                &data(AssetGroupKind::Collection),
+                &AssetGroupContext::default()
+            ),
+            vec![AssetGroupViolation::MissingCollectionOrProgram]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_data() {
+        let (asset, collection, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let ctx = context(collection, owner, None);
+
+        assert_eq!(
+            validate_asset_group(&asset, &[collection, owner], &[], &ctx),
+            vec![AssetGroupViolation::MissingDiscriminator]
+        );
+        assert_eq!(
+            validate_asset_group(&asset, &[collection, owner], &[0; 8], &ctx),
+            vec![AssetGroupViolation::UnknownDiscriminator]
+        );
+    }
+
+    #[test]
+    fn checks_rendered_fields() {
+        let owner = Pubkey::new_unique().to_string();
+        let json = serde_json::json!({
+            "name": "name",
+            "symbol": "symbol",
+            "uri": "uri",
+            "owner": owner,
+            "delegate": owner,
+        });
+        assert_eq!(validate_rendered_asset(json.to_string().as_bytes()), vec![]);
+
+        let json = serde_json::json!({ "name": "name", "symbol": 1, "uri": "uri" });
+        assert_eq!(
+            validate_rendered_asset(json.to_string().as_bytes()),
+            vec![
+                AssetGroupViolation::MissingRenderedField("symbol"),
+                AssetGroupViolation::MissingRenderedField("delegate"),
+                AssetGroupViolation::MissingRenderedField("owner"),
+            ]
+        );
+        assert_eq!(
+            validate_rendered_asset(b"[]"),
+            vec![AssetGroupViolation::InvalidRenderedJson]
+        );
+        assert_eq!(
+            validate_rendered_asset(b"{"),
+            vec![AssetGroupViolation::InvalidRenderedJson]
+        );
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "strict-spec"))]
+    #[should_panic(expected = "violates sRFC19")]
+    fn emitted_metadata_is_checked_against_its_context() {
+        let (collection, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let asset = NftMetadataAsset {
+            asset_id: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            collection: owner,
+            delegate: collection,
+            pubkeys: vec![],
+            data: vec![],
+        };
+
+        asset.checked_pubkeys(
+            &data(AssetGroupKind::Metadata),
+            &context(collection, owner, None),
+        );
+    }
+}
@@ -1,102 +1,612 @@
 extern crate proc_macro;
+extern crate proc_macro2;
 extern crate quote;
 extern crate syn;
 
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::quote;
-use syn::parse_macro_input;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Token};
 
-#[proc_macro]
-pub fn emit_create_nft_collection(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let asset_group = parse_macro_input!(input as syn::Expr);
+/// `emit_*!(asset_group [, version = <u8>] [, flags = <u8>] [, context = <AssetGroupContext>])`
+///
+/// Without an explicit version, events are emitted as spec v0 (discriminator then payload).
+/// `context` is what the group is checked against; the metadata macros require it.
+struct EmitArgs {
+    asset_group: syn::Expr,
+    version: Option<syn::Expr>,
+    flags: Option<syn::Expr>,
+    context: Option<syn::Expr>,
+}
+
+impl Parse for EmitArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let asset_group = input.parse()?;
+        let mut version = None;
+        let mut flags = None;
+        let mut context = None;
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name: syn::Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: syn::Expr = input.parse()?;
+            match name.to_string().as_str() {
+                "version" => version = Some(value),
+                "flags" => flags = Some(value),
+                "context" => context = Some(value),
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "expected `version = ...`, `flags = ...` or `context = ...`",
+                    ))
+                }
+            }
+        }
+
+        Ok(EmitArgs {
+            asset_group,
+            version,
+            flags,
+            context,
+        })
+    }
+}
+
+impl EmitArgs {
+    fn version(&self) -> TokenStream {
+        match &self.version {
+            Some(version) => quote! { #version },
+            None => quote! { ::nft_events::SPEC_VERSION_V0 },
+        }
+    }
+
+    fn flags(&self) -> TokenStream {
+        match &self.flags {
+            Some(flags) => quote! { #flags },
+            None => quote! { 0u8 },
+        }
+    }
+}
+
+/// Emits `event` with `emit!` when the calling program is built with `log-events`,
+/// otherwise with `emit_cpi!`.
+///
+/// The choice is left to `#[cfg]`s in the expansion, so that it follows the features of
+/// the program using the macro rather than the features this crate was built with,
+/// which cargo unifies across the whole workspace.
+fn emit_event(event: TokenStream) -> TokenStream {
+    quote! {
+        #[cfg(feature = "log-events")]
+        emit!(#event);
+        #[cfg(not(feature = "log-events"))]
+        emit_cpi!({ #event });
+    }
+}
+
+/// Header flags with the emission mode added. v0 data has no flags byte, so it is left as is.
+fn header_flags(version: &TokenStream, flags: &TokenStream) -> TokenStream {
+    quote! {
+        {
+            #[cfg(feature = "log-events")]
+            let header_flags = if #version == ::nft_events::SPEC_VERSION_V0 {
+                #flags
+            } else {
+                #flags | ::nft_events::FLAG_LOG_EVENT
+            };
+            #[cfg(not(feature = "log-events"))]
+            let header_flags = #flags;
+            header_flags
+        }
+    }
+}
+
+fn expand_collection(args: EmitArgs, event: &str) -> proc_macro::TokenStream {
+    let asset_group = &args.asset_group;
+    let version = args.version();
+    // Collections only have to list themselves or the program first, which needs nothing
+    // but the program id
+    let context = match &args.context {
+        Some(context) => quote! { #context },
+        None => quote! {
+            ::nft_events::AssetGroupContext {
+                program_id: crate::ID,
+                ..Default::default()
+            }
+        },
+    };
+    let flags = header_flags(&quote! { asset_version }, &args.flags());
+    let event = Ident::new(event, Span::call_site());
+    let emit = emit_event(quote! {
+        #event {
+            authority: asset_group.authority,
+            asset_id: asset_group.asset_id,
+            pubkeys: asset_group.pubkeys,
+            data: collection_data,
+        }
+    });
 
     proc_macro::TokenStream::from(quote! {
         {
-            let mut collection_data = get_collection_discriminator()?;
-            collection_data.extend_from_slice(&#asset_group.data);
-
-            emit_cpi!({
-                CudCreate {
-                    authority: #asset_group.authority,
-                    asset_id: #asset_group.asset_id,
-                    pubkeys: #asset_group.pubkeys,
-                    data: collection_data,
-                }
-            });
+            let asset_group = #asset_group;
+            let asset_version: u8 = #version;
+            let collection_data = ::nft_events::encode_asset_data(
+                ::nft_events::AssetGroupKind::Collection,
+                asset_version,
+                #flags,
+                &asset_group.data,
+            )?;
+
+            ::nft_events::debug_assert_asset_group(
+                &asset_group.asset_id,
+                &asset_group.pubkeys,
+                &collection_data,
+                &#context,
+            );
+
+            #emit
         }
     })
 }
 
-#[proc_macro]
-pub fn emit_update_nft_collection(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let asset_group = parse_macro_input!(input as syn::Expr);
+fn expand_metadata(args: EmitArgs, event: &str) -> proc_macro::TokenStream {
+    let asset_group = &args.asset_group;
+    let context = match &args.context {
+        Some(context) => context,
+        None => {
+            return syn::Error::new(
+                Span::call_site(),
+                "metadata is checked against the asset's collection, owner and delegate: \
+                 pass `context = ...`, e.g. the account's `asset_group_context()`",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+    let version = args.version();
+    let flags = header_flags(&quote! { asset_version }, &args.flags());
+    let event = Ident::new(event, Span::call_site());
+    let emit = emit_event(quote! {
+        #event {
+            authority: asset_group.authority,
+            asset_id: asset_group.asset_id,
+            pubkeys,
+            data: asset_data,
+        }
+    });
 
     proc_macro::TokenStream::from(quote! {
         {
-            let mut collection_data = get_collection_discriminator()?;
-            collection_data.extend_from_slice(&#asset_group.data);
-
-            emit_cpi!({
-                CudUpdate {
-                    authority: #asset_group.authority,
-                    asset_id: #asset_group.asset_id,
-                    pubkeys: #asset_group.pubkeys,
-                    data: collection_data,
-                }
-            });
+            let asset_group = #asset_group;
+            let asset_version: u8 = #version;
+            let asset_data = ::nft_events::encode_asset_data(
+                ::nft_events::AssetGroupKind::Metadata,
+                asset_version,
+                #flags,
+                &asset_group.data,
+            )?;
+
+            let pubkeys = asset_group.checked_pubkeys(&asset_data, &#context);
+
+            #emit
         }
     })
 }
 
+#[proc_macro]
+pub fn emit_create_nft_collection(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_collection(parse_macro_input!(input as EmitArgs), "CudCreate")
+}
+
+#[proc_macro]
+pub fn emit_update_nft_collection(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_collection(parse_macro_input!(input as EmitArgs), "CudUpdate")
+}
+
 #[proc_macro]
 pub fn emit_create_nft_metadata(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let asset_group = parse_macro_input!(input as syn::Expr);
+    expand_metadata(parse_macro_input!(input as EmitArgs), "CudCreate")
+}
+
+#[proc_macro]
+pub fn emit_update_nft_metadata(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_metadata(parse_macro_input!(input as EmitArgs), "CudUpdate")
+}
+
+fn expand_transfer(input: syn::Expr) -> proc_macro::TokenStream {
+    let emit = emit_event(quote! {
+        NftTransferred {
+            asset: transfer.asset,
+            from: transfer.from,
+            to: transfer.to,
+            authority: transfer.authority,
+        }
+    });
 
     proc_macro::TokenStream::from(quote! {
         {
-            let mut asset_data = get_metadata_discriminator()?;
-            asset_data.extend_from_slice(&#asset_group.data);
-
-            let mut pubkeys = vec![
-                #asset_group.collection.key().clone(),
-                #asset_group.delegate.key().clone(),
-            ];
-            pubkeys.extend_from_slice(&#asset_group.pubkeys);
-
-            emit_cpi!({
-                CudCreate {
-                    authority: #asset_group.authority,
-                    asset_id: #asset_group.asset_id,
-                    pubkeys,
-                    data: asset_data,
-                }
-            });
+            let transfer = #input;
+            #emit
+        }
+    })
+}
+
+fn expand_delegation(input: syn::Expr, event: &str) -> proc_macro::TokenStream {
+    let event = Ident::new(event, Span::call_site());
+    let emit = emit_event(quote! {
+        #event {
+            asset: delegation.asset,
+            owner: delegation.owner,
+            delegate: delegation.delegate,
+            authority: delegation.authority,
+        }
+    });
+
+    proc_macro::TokenStream::from(quote! {
+        {
+            let delegation = #input;
+            #emit
         }
     })
 }
 
+/// Emits `NftTransferred` from an `NftTransfer`. The program must declare the event.
 #[proc_macro]
-pub fn emit_update_nft_metadata(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let asset_group = parse_macro_input!(input as syn::Expr);
+pub fn emit_nft_transferred(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_transfer(parse_macro_input!(input as syn::Expr))
+}
+
+/// Emits `NftDelegated` from an `NftDelegation`. The program must declare the event.
+#[proc_macro]
+pub fn emit_nft_delegated(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_delegation(parse_macro_input!(input as syn::Expr), "NftDelegated")
+}
+
+/// Emits `NftRevoked` from an `NftDelegation`. The program must declare the event.
+#[proc_macro]
+pub fn emit_nft_revoked(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_delegation(parse_macro_input!(input as syn::Expr), "NftRevoked")
+}
 
+/// Builds the `EventEmitter` for the calling program's emission mode. In CPI mode `ctx`
+/// must be in scope and its accounts struct must be `#[event_cpi]`: events are emitted
+/// through Anchor's `emit_cpi!`, expanded here in the program.
+#[proc_macro]
+pub fn event_emitter(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(quote! {
         {
-            let mut asset_data = get_metadata_discriminator()?;
-            asset_data.extend_from_slice(&#asset_group.data);
-
-            let mut pubkeys = vec![
-                #asset_group.collection.key().clone(),
-                #asset_group.delegate.key().clone(),
-            ];
-            pubkeys.extend_from_slice(&#asset_group.pubkeys);
-
-            emit_cpi!({
-                CudUpdate {
-                    authority: #asset_group.authority,
-                    asset_id: #asset_group.asset_id,
+            #[cfg(feature = "log-events")]
+            let emitter = ::nft_events::EventEmitter::Log;
+            #[cfg(not(feature = "log-events"))]
+            let emitter = ::nft_events::EventEmitter::Cpi(::std::boxed::Box::new(
+                |event: ::nft_events::EncodedEvent| -> ::anchor_lang::Result<()> {
+                    emit_cpi!(event);
+                    Ok(())
+                },
+            ));
+            emitter
+        }
+    })
+}
+
+/// Which AssetGroup slot a field of a `#[derive(NftAsset)]` struct fills
+#[derive(PartialEq)]
+enum AssetRole {
+    Collection,
+    Owner,
+    Delegate,
+    Extra,
+}
+
+struct NftAssetFields {
+    collection: Option<Ident>,
+    owner: Option<Ident>,
+    delegate: Option<Ident>,
+    extras: Vec<Ident>,
+    payload: Option<syn::Path>,
+    flags: Option<syn::Expr>,
+}
+
+fn parse_asset_attr(attr: &syn::Attribute) -> syn::Result<Vec<syn::NestedMeta>> {
+    match attr.parse_meta()? {
+        syn::Meta::List(list) => Ok(list.nested.into_iter().collect()),
+        meta => Err(syn::Error::new_spanned(meta, "expected #[asset(...)]")),
+    }
+}
+
+fn parse_nft_asset_fields(input: &syn::DeriveInput) -> syn::Result<NftAssetFields> {
+    let mut parsed = NftAssetFields {
+        collection: None,
+        owner: None,
+        delegate: None,
+        extras: vec![],
+        payload: None,
+        flags: None,
+    };
+
+    for attr in input.attrs.iter().filter(|a| a.path.is_ident("asset")) {
+        for nested in parse_asset_attr(attr)? {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
+                    let value = match &nv.lit {
+                        syn::Lit::Str(value) => value,
+                        lit => return Err(syn::Error::new_spanned(lit, "expected a string")),
+                    };
+                    if nv.path.is_ident("payload") {
+                        parsed.payload = Some(value.parse()?);
+                    } else if nv.path.is_ident("flags") {
+                        parsed.flags = Some(value.parse()?);
+                    } else {
+                        return Err(syn::Error::new_spanned(
+                            nv.path,
+                            "expected `payload = \"...\"` or `flags = \"...\"`",
+                        ));
+                    }
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "expected `payload = \"...\"` or `flags = \"...\"`",
+                    ))
+                }
+            }
+        }
+    }
+
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "NftAsset can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    for field in fields {
+        let ident = field.ident.clone().unwrap();
+        for attr in field.attrs.iter().filter(|a| a.path.is_ident("asset")) {
+            for nested in parse_asset_attr(attr)? {
+                let role = match &nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("collection") => {
+                        AssetRole::Collection
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("owner") => {
+                        AssetRole::Owner
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("delegate") => {
+                        AssetRole::Delegate
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("extra") => {
+                        AssetRole::Extra
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "expected one of `collection`, `owner`, `delegate` or `extra`",
+                        ))
+                    }
+                };
+                let slot = match role {
+                    AssetRole::Collection => &mut parsed.collection,
+                    AssetRole::Owner => &mut parsed.owner,
+                    AssetRole::Delegate => &mut parsed.delegate,
+                    AssetRole::Extra => {
+                        parsed.extras.push(ident.clone());
+                        continue;
+                    }
+                };
+                if slot.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "only one field can have this role",
+                    ));
+                }
+                *slot = Some(ident.clone());
+            }
+        }
+    }
+
+    if parsed.owner.is_none() && parsed.delegate.is_none() {
+        return Err(syn::Error::new_spanned(
+            input,
+            "NftAsset needs an #[asset(owner)] or #[asset(delegate)] field",
+        ));
+    }
+
+    Ok(parsed)
+}
+
+fn expand_nft_asset(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let fields = parse_nft_asset_fields(&input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // The authority is the owner, falling back to the delegate when there is no owner field.
+    // The 2nd pubkey is the delegate while delegated, otherwise the owner.
+    let (authority, owner_or_delegate) = match (&fields.owner, &fields.delegate) {
+        (Some(owner), Some(delegate)) => (
+            quote! { self.#owner },
+            quote! {
+                if self.#delegate != self.#owner
+                    && self.#delegate != ::anchor_lang::prelude::Pubkey::default()
+                {
+                    self.#delegate
+                } else {
+                    self.#owner
+                }
+            },
+        ),
+        (Some(owner), None) => (quote! { self.#owner }, quote! { self.#owner }),
+        (None, Some(delegate)) => (quote! { self.#delegate }, quote! { self.#delegate }),
+        (None, None) => unreachable!(),
+    };
+
+    // What the account itself says pubkeys[0] and pubkeys[1] must be, for the spec checks
+    let context_collection = match &fields.collection {
+        Some(collection) => quote! { Some(self.#collection) },
+        None => quote! { None },
+    };
+    let (context_owner, context_delegate) = match (&fields.owner, &fields.delegate) {
+        (Some(owner), Some(delegate)) => (
+            quote! { Some(self.#owner) },
+            quote! {
+                if self.#delegate != self.#owner
+                    && self.#delegate != ::anchor_lang::prelude::Pubkey::default()
+                {
+                    Some(self.#delegate)
+                } else {
+                    None
+                }
+            },
+        ),
+        (Some(owner), None) => (quote! { Some(self.#owner) }, quote! { None }),
+        (None, Some(delegate)) => (quote! { None }, quote! { Some(self.#delegate) }),
+        (None, None) => unreachable!(),
+    };
+
+    // Without a collection field the struct is itself a collection, listed first
+    let (kind, leading_pubkeys) = match &fields.collection {
+        Some(collection) => (
+            quote! { ::nft_events::AssetGroupKind::Metadata },
+            quote! { vec![self.#collection, #owner_or_delegate] },
+        ),
+        None => (
+            quote! { ::nft_events::AssetGroupKind::Collection },
+            quote! { vec![asset_id] },
+        ),
+    };
+    let extras = &fields.extras;
+    let payload = match &fields.payload {
+        Some(payload) => quote! { self.#payload()? },
+        None => quote! { Vec::<u8>::new() },
+    };
+    let flags = match &fields.flags {
+        Some(flags) => quote! { #flags },
+        None => quote! { 0u8 },
+    };
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Builds this account's AssetGroup in sRFC19 order: collection (or itself),
+            /// then delegate or owner, then any `#[asset(extra)]` keys
+            pub fn to_asset_group(
+                &self,
+                asset_id: ::anchor_lang::prelude::Pubkey,
+            ) -> ::anchor_lang::Result<::nft_events::AssetGroup> {
+                self.__to_asset_group(asset_id, #flags)
+            }
+
+            /// Same as `to_asset_group`, with the header flagged for how `emitter` emits
+            pub fn to_asset_group_for(
+                &self,
+                asset_id: ::anchor_lang::prelude::Pubkey,
+                emitter: &::nft_events::EventEmitter,
+            ) -> ::anchor_lang::Result<::nft_events::AssetGroup> {
+                self.__to_asset_group(asset_id, #flags | emitter.mode_flag())
+            }
+
+            fn __to_asset_group(
+                &self,
+                asset_id: ::anchor_lang::prelude::Pubkey,
+                flags: u8,
+            ) -> ::anchor_lang::Result<::nft_events::AssetGroup> {
+                let mut pubkeys: Vec<::anchor_lang::prelude::Pubkey> = #leading_pubkeys;
+                #(pubkeys.push(self.#extras);)*
+
+                let data = ::nft_events::encode_asset_data(
+                    #kind,
+                    ::nft_events::CURRENT_SPEC_VERSION,
+                    flags,
+                    &#payload,
+                )?;
+
+                Ok(::nft_events::AssetGroup {
+                    asset_id,
+                    authority: #authority,
                     pubkeys,
-                    data: asset_data,
+                    data,
+                })
+            }
+
+            /// The collection, owner and delegate this account's AssetGroup must list,
+            /// read from the account's fields rather than from the group
+            pub fn asset_group_context(&self) -> ::nft_events::AssetGroupContext {
+                ::nft_events::AssetGroupContext {
+                    program_id: crate::ID,
+                    collection: #context_collection,
+                    owner: #context_owner,
+                    delegate: #context_delegate,
                 }
-            });
+            }
+
+            /// Emits this account's AssetGroup as a `CudCreate`
+            pub fn emit_create(
+                &self,
+                asset_id: ::anchor_lang::prelude::Pubkey,
+                emitter: &::nft_events::EventEmitter,
+            ) -> ::anchor_lang::Result<()> {
+                let group = self.to_asset_group_for(asset_id, emitter)?;
+                ::nft_events::debug_assert_asset_group(
+                    &group.asset_id,
+                    &group.pubkeys,
+                    &group.data,
+                    &self.asset_group_context(),
+                );
+                emitter.emit(&CudCreate {
+                    asset_id: group.asset_id,
+                    authority: group.authority,
+                    pubkeys: group.pubkeys,
+                    data: group.data,
+                })?;
+                Ok(())
+            }
+
+            /// Emits this account's AssetGroup as a `CudUpdate`
+            pub fn emit_update(
+                &self,
+                asset_id: ::anchor_lang::prelude::Pubkey,
+                emitter: &::nft_events::EventEmitter,
+            ) -> ::anchor_lang::Result<()> {
+                let group = self.to_asset_group_for(asset_id, emitter)?;
+                ::nft_events::debug_assert_asset_group(
+                    &group.asset_id,
+                    &group.pubkeys,
+                    &group.data,
+                    &self.asset_group_context(),
+                );
+                emitter.emit(&CudUpdate {
+                    asset_id: group.asset_id,
+                    authority: group.authority,
+                    pubkeys: group.pubkeys,
+                    data: group.data,
+                })?;
+                Ok(())
+            }
         }
     })
 }
+
+/// Generates `to_asset_group`, `to_asset_group_for`, `asset_group_context`, `emit_create`
+/// and `emit_update` for an account struct. The emit methods take an `EventEmitter`, see
+/// `event_emitter!`, and check the group against `asset_group_context()` first.
+///
+/// Fields are tagged with `#[asset(collection)]`, `#[asset(owner)]`, `#[asset(delegate)]`
+/// or `#[asset(extra)]`. A struct without a collection field is treated as a collection.
+/// `#[asset(payload = "method", flags = "FLAGS")]` on the struct sets the data payload,
+/// where `method` is a `fn(&self) -> Result<Vec<u8>>`.
+/// The program must declare `CudCreate` and `CudUpdate` events.
+#[proc_macro_derive(NftAsset, attributes(asset))]
+pub fn derive_nft_asset(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    match expand_nft_asset(input) {
+        Ok(tokens) => proc_macro::TokenStream::from(tokens),
+        Err(e) => proc_macro::TokenStream::from(e.to_compile_error()),
+    }
+}
@@ -1,36 +1,130 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
 use anchor_lang::Discriminator;
+use nft_events::tlv::{self, encode_tlv, TlvWriter};
 use nft_events::{
-    emit_create_nft_collection, emit_create_nft_metadata, emit_update_nft_collection,
-    emit_update_nft_metadata, get_collection_discriminator, get_metadata_discriminator,
-    NftCollectionAsset, NftMetadataAsset,
+    debug_assert_asset_group, emit_nft_delegated, emit_nft_revoked, emit_nft_transferred,
+    emit_update_nft_collection, event_emitter, new_creators, verify_creator, Creator, CreatorError,
+    CudCreateBatch, NftAsset, NftCollectionAsset, NftDelegation, NftTransfer, RenderFormat,
+    RenderRequest, CREATOR_LEN, CURRENT_SPEC_VERSION, FLAG_TLV_PAYLOAD, MAX_RENDERED_LEN,
 };
-use serde::{self, Serialize};
-use serde_json;
 
 use additional_accounts_request::{IAccountMeta, PreflightPayload};
-use bs58_pubkey::serde_pubkey;
+use bs58_pubkey::json::JsonWriter;
 
 declare_id!("G52tBxQ813dTNqvehtAsG4vcpJG3PNrVAMxVs1Vh1M89");
 
+/// TLV key of a metadata's `revealed` flag, a single byte
+pub const REVEALED: &str = "revealed";
+
 #[program]
 pub mod nft_style_one {
 
-    use anchor_lang::solana_program::program::set_return_data;
+    use anchor_lang::solana_program::program::{set_return_data, MAX_RETURN_DATA};
 
     use super::*;
 
-    pub fn init_collection(ctx: Context<InitCollection>, num_items: u32) -> Result<()> {
-        ctx.accounts.collection.authority = *ctx.accounts.owner.key;
-        ctx.accounts.collection.num_items = num_items;
+    pub fn init_collection(
+        ctx: Context<InitCollection>,
+        num_items: u32,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        ctx.accounts.collection.set_inner(Collection {
+            authority: *ctx.accounts.owner.key,
+            num_items,
+            supply: 0,
+            minted: 0,
+            pending_authority: None,
+            name,
+            symbol,
+            uri,
+        });
 
         // Issue a collection
-        emit_create_nft_collection!(NftCollectionAsset {
-            authority: ctx.accounts.owner.key(),
-            asset_id: ctx.accounts.collection.key(),
-            pubkeys: vec![ctx.accounts.collection.key().clone()],
-            data: vec![],
-        });
+        ctx.accounts
+            .collection
+            .emit_create(ctx.accounts.collection.key(), &event_emitter!())?;
+        Ok(())
+    }
+
+    /// Upgrades a collection created by the first release, which only stored its authority and
+    /// `num_items`.
+    ///
+    /// That layout did not count its assets, so the authority supplies `supply`, the assets
+    /// minted and not burned, and `minted`, the number `mint_next` continues from. `minted`
+    /// must be past every number already used, or `mint_next` will collide with existing
+    /// metadata. The account is reallocated in place and the extra rent is paid by `authority`.
+    pub fn migrate_collection(
+        ctx: Context<MigrateCollection>,
+        name: String,
+        symbol: String,
+        uri: String,
+        supply: u32,
+        minted: u32,
+    ) -> Result<()> {
+        let info = ctx.accounts.collection.to_account_info();
+        let mut collection = Collection::from_legacy(&info.try_borrow_data()?)?;
+        require_keys_eq!(
+            collection.authority,
+            ctx.accounts.authority.key(),
+            NftStyleOneError::UnauthorizedAuthority
+        );
+        collection.set_counts(supply, minted)?;
+        collection.name = name;
+        collection.symbol = symbol;
+        collection.uri = uri;
+
+        let space = collection.space();
+        let rent = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(info.lamports());
+        if rent > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        info.realloc(space, false)?;
+        collection.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        emit_update_nft_collection!(
+            collection.to_collection_asset(info.key())?,
+            version = CURRENT_SPEC_VERSION,
+            flags = FLAG_TLV_PAYLOAD
+        );
+        Ok(())
+    }
+
+    /// Starts handing the collection to `new_authority`, who must `accept_authority`.
+    /// Proposing `None` cancels a pending handoff.
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.collection.pending_authority = new_authority;
+        Ok(())
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        ctx.accounts
+            .collection
+            .accept_authority(ctx.accounts.new_authority.key)?;
+
+        emit_update_nft_collection!(
+            ctx.accounts
+                .collection
+                .to_collection_asset(ctx.accounts.collection.key())?,
+            version = CURRENT_SPEC_VERSION,
+            flags = FLAG_TLV_PAYLOAD
+        );
         Ok(())
     }
 
@@ -40,156 +134,1460 @@ pub mod nft_style_one {
         name: String,
         symbol: String,
         uri: String,
+        attributes: Vec<Attribute>,
+        creators: Vec<Creator>,
     ) -> Result<()> {
-        ctx.accounts.asset.collection = ctx.accounts.collection.key();
-        ctx.accounts.asset.collection_num = collection_num;
-        ctx.accounts.asset.name = name.clone();
-        ctx.accounts.asset.symbol = symbol.clone();
-        ctx.accounts.asset.uri = uri.clone();
-        ctx.accounts.asset.owner = *ctx.accounts.owner.key;
-        ctx.accounts.asset.delegate = *ctx.accounts.owner.key;
+        ctx.accounts
+            .collection
+            .check_mint(ctx.accounts.owner.key, collection_num, 1)?;
+        ctx.accounts.collection.record_mint(1)?;
+        ctx.accounts.asset.set_inner(Metadata::new(
+            ctx.accounts.collection.key(),
+            collection_num,
+            *ctx.accounts.owner.key,
+            MintItem {
+                name,
+                symbol,
+                uri,
+                attributes,
+                creators,
+            },
+        )?);
 
         // Issue a metadata
-        emit_create_nft_metadata!(NftMetadataAsset {
-            authority: ctx.accounts.owner.key(),
-            asset_id: ctx.accounts.asset.key(),
-            collection: ctx.accounts.collection.key().clone(),
-            delegate: ctx.accounts.owner.key().clone(),
-            pubkeys: vec![],
-            data: vec![],
-        });
+        ctx.accounts
+            .asset
+            .emit_create(ctx.accounts.asset.key(), &event_emitter!())?;
 
         Ok(())
     }
 
-    pub fn preflight_transfer(ctx: Context<ITransfer>) -> Result<()> {
-        let event_authority =
-            Pubkey::find_program_address(&[b"__event_authority"], &ctx.program_id).0;
-
-        set_return_data(
-            &PreflightPayload {
-                accounts: vec![
-                    IAccountMeta {
-                        pubkey: ctx.accounts.asset.collection.key(),
-                        signer: false,
-                        writable: false,
-                    },
-                    IAccountMeta {
-                        pubkey: event_authority,
-                        signer: false,
-                        writable: false,
-                    },
-                    IAccountMeta {
-                        pubkey: *ctx.program_id,
-                        signer: false,
-                        writable: false,
-                    },
-                ],
+    /// Mints the next asset of the collection, numbered by its `minted` counter.
+    ///
+    /// Collections minted this way should not also mint explicit numbers with `mint`,
+    /// since the counter does not skip numbers that are already taken.
+    pub fn mint_next(
+        ctx: Context<MintNext>,
+        name: String,
+        symbol: String,
+        uri: String,
+        attributes: Vec<Attribute>,
+        creators: Vec<Creator>,
+    ) -> Result<()> {
+        let collection_num = ctx.accounts.collection.minted;
+        ctx.accounts
+            .collection
+            .check_mint(ctx.accounts.owner.key, collection_num, 1)?;
+        ctx.accounts.collection.record_mint(1)?;
+        ctx.accounts.asset.set_inner(Metadata::new(
+            ctx.accounts.collection.key(),
+            collection_num,
+            *ctx.accounts.owner.key,
+            MintItem {
+                name,
+                symbol,
+                uri,
+                attributes,
+                creators,
+            },
+        )?);
+
+        ctx.accounts
+            .asset
+            .emit_create(ctx.accounts.asset.key(), &event_emitter!())?;
+
+        Ok(())
+    }
+
+    /// Mints `items.len()` assets numbered from `start_num`, and emits them as a single `CudCreateBatch`.
+    ///
+    /// The metadata PDAs for each number must be passed, in order, as remaining accounts.
+    pub fn mint_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintBatch<'info>>,
+        start_num: u32,
+        items: Vec<MintItem>,
+    ) -> Result<()> {
+        if items.len() != ctx.remaining_accounts.len() {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+
+        let collection = ctx.accounts.collection.key();
+        let owner = *ctx.accounts.owner.key;
+        let count =
+            u32::try_from(items.len()).map_err(|_| NftStyleOneError::CollectionNumOutOfRange)?;
+        ctx.accounts
+            .collection
+            .check_mint(&owner, start_num, count)?;
+        ctx.accounts.collection.record_mint(count)?;
+        let rent = Rent::get()?;
+        let emitter = event_emitter!();
+
+        let mut asset_groups = Vec::with_capacity(items.len());
+        for (i, (item, asset_info)) in items
+            .into_iter()
+            .zip(ctx.remaining_accounts.iter())
+            .enumerate()
+        {
+            let collection_num = start_num
+                .checked_add(i as u32)
+                .ok_or(ProgramError::InvalidArgument)?;
+            let num_bytes = collection_num.to_le_bytes();
+            let (expected, bump) = Pubkey::find_program_address(
+                &[collection.as_ref(), b"metadata".as_ref(), &num_bytes],
+                ctx.program_id,
+            );
+            if expected != asset_info.key() {
+                return Err(ErrorCode::ConstraintSeeds.into());
             }
-            .try_to_vec()
-            .unwrap(),
-        );
+
+            let metadata = Metadata::new(collection, collection_num, owner, item)?;
+            let space = 8 + metadata.try_to_vec()?.len();
+            create_pda_account(
+                &ctx.accounts.owner.to_account_info(),
+                asset_info,
+                &ctx.accounts.system_program.to_account_info(),
+                rent.minimum_balance(space),
+                space,
+                ctx.program_id,
+                &[
+                    collection.as_ref(),
+                    b"metadata".as_ref(),
+                    &num_bytes,
+                    &[bump],
+                ],
+            )?;
+            metadata.try_serialize(&mut &mut asset_info.try_borrow_mut_data()?[..])?;
+
+            let asset_group = metadata.to_asset_group_for(asset_info.key(), &emitter)?;
+            debug_assert_asset_group(
+                &asset_group.asset_id,
+                &asset_group.pubkeys,
+                &asset_group.data,
+                &metadata.asset_group_context(),
+            );
+            asset_groups.push(asset_group);
+        }
+
+        emitter.emit(&CudCreateBatch { asset_groups })?;
+        Ok(())
+    }
+
+    /// Opens the collection to `public_mint` under the given price, schedule and allowlist
+    pub fn init_mint_config(ctx: Context<InitMintConfig>, args: MintConfigArgs) -> Result<()> {
+        ctx.accounts
+            .mint_config
+            .set_inner(MintConfig::new(ctx.accounts.collection.key(), args)?);
+        Ok(())
+    }
+
+    pub fn update_mint_config(ctx: Context<UpdateMintConfig>, args: MintConfigArgs) -> Result<()> {
+        let collection = ctx.accounts.mint_config.collection;
+        ctx.accounts
+            .mint_config
+            .set_inner(MintConfig::new(collection, args)?);
+        Ok(())
+    }
+
+    /// Mints the next asset of the collection to any wallet the mint config allows,
+    /// paying the config's price to its recipient.
+    ///
+    /// `proof` is the allowlist merkle proof for the minter, and is ignored when the
+    /// config has no allowlist.
+    pub fn public_mint(ctx: Context<PublicMint>, proof: Vec<[u8; 32]>) -> Result<()> {
+        let minter = *ctx.accounts.minter.key;
+        ctx.accounts.mint_config.check_public_mint(
+            &minter,
+            Clock::get()?.slot,
+            ctx.accounts.wallet_mints.minted,
+            &proof,
+        )?;
+
+        let collection_num = ctx.accounts.collection.minted;
+        ctx.accounts.collection.check_range(collection_num, 1)?;
+        ctx.accounts.collection.record_mint(1)?;
+        ctx.accounts.wallet_mints.record_mint()?;
+
+        let price = ctx.accounts.mint_config.price;
+        if price > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.minter.to_account_info(),
+                        to: ctx.accounts.recipient.to_account_info(),
+                    },
+                ),
+                price,
+            )?;
+        }
+
+        ctx.accounts.asset.set_inner(Metadata::new(
+            ctx.accounts.collection.key(),
+            collection_num,
+            minter,
+            ctx.accounts.collection.placeholder(collection_num),
+        )?);
+
+        ctx.accounts
+            .asset
+            .emit_create(ctx.accounts.asset.key(), &event_emitter!())?;
+
         Ok(())
     }
 
+    pub fn preflight_transfer(ctx: Context<ITransfer>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            false,
+        )?);
+        Ok(())
+    }
+
+    /// Moves the asset to `destination`, signed by its owner or delegate. Clears any delegation.
     pub fn transfer(ctx: Context<TransferMe>) -> Result<()> {
-        assert!(
-            ctx.accounts.asset.owner == *ctx.accounts.authority.key
-                || ctx.accounts.asset.delegate == *ctx.accounts.authority.key
-        );
-        ctx.accounts.asset.owner = *ctx.accounts.destination.key;
+        ctx.accounts
+            .asset
+            .check_owner_or_delegate(ctx.accounts.authority.key)?;
+        let from = ctx.accounts.asset.owner;
+        ctx.accounts
+            .asset
+            .transfer_to(*ctx.accounts.destination.key);
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        emit_nft_transferred!(NftTransfer {
+            asset: ctx.accounts.asset.key(),
+            from,
+            to: ctx.accounts.destination.key(),
+            authority: ctx.accounts.authority.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn preflight_delegate(ctx: Context<IDelegate>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            false,
+        )?);
+        Ok(())
+    }
+
+    /// Lets `delegate` transfer the asset until the owner revokes it or the asset is transferred
+    pub fn delegate(ctx: Context<DelegateMe>) -> Result<()> {
+        ctx.accounts.asset.set_delegate(*ctx.accounts.delegate.key);
 
-        emit_update_nft_metadata!(NftMetadataAsset {
-            authority: ctx.accounts.destination.key(),
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        emit_nft_delegated!(NftDelegation {
+            asset: ctx.accounts.asset.key(),
+            owner: ctx.accounts.owner.key(),
+            delegate: ctx.accounts.delegate.key(),
+            authority: ctx.accounts.owner.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn preflight_revoke(ctx: Context<IRevoke>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            false,
+        )?);
+        Ok(())
+    }
+
+    pub fn revoke(ctx: Context<RevokeMe>) -> Result<()> {
+        let delegate = ctx.accounts.asset.delegate;
+        ctx.accounts.asset.revoke();
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        emit_nft_revoked!(NftDelegation {
+            asset: ctx.accounts.asset.key(),
+            owner: ctx.accounts.owner.key(),
+            delegate,
+            authority: ctx.accounts.owner.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn preflight_burn(ctx: Context<IBurn>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            true,
+        )?);
+        Ok(())
+    }
+
+    /// Destroys the asset, refunding its rent to `rent_recipient`
+    pub fn burn(ctx: Context<BurnMe>) -> Result<()> {
+        ctx.accounts
+            .asset
+            .check_owner_or_delegate(ctx.accounts.authority.key)?;
+        ctx.accounts.collection.record_burn()?;
+
+        event_emitter!().emit(&CudDelete {
             asset_id: ctx.accounts.asset.key(),
-            collection: ctx.accounts.collection.key().clone(),
-            delegate: ctx.accounts.destination.key().clone(),
-            pubkeys: vec![],
-            data: vec![],
+        })?;
+        Ok(())
+    }
+
+    /// Rewrites name, symbol, uri and attributes, resizing the account to fit them.
+    /// In a collection with a provenance commitment, name and uri only change through `reveal`.
+    ///
+    /// Growing the account charges the extra rent to `authority`, and shrinking it refunds the difference.
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+        attributes: Vec<Attribute>,
+    ) -> Result<()> {
+        let committed = ctx.accounts.provenance.owner == ctx.program_id;
+        ctx.accounts
+            .asset
+            .check_hidden_update(&name, &uri, committed)?;
+        ctx.accounts.asset.update(name, symbol, uri, attributes)?;
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        Ok(())
+    }
+
+    /// Upgrades a metadata account created before it had `is_mutable`, attributes and creators.
+    ///
+    /// The account is reallocated in place and the extra rent is paid by its owner.
+    pub fn migrate_metadata(ctx: Context<MigrateMetadata>) -> Result<()> {
+        let info = ctx.accounts.asset.to_account_info();
+        let metadata = Metadata::from_legacy(&info.try_borrow_data()?)?;
+        require_keys_eq!(
+            metadata.owner,
+            ctx.accounts.owner.key(),
+            NftStyleOneError::OwnerMismatch
+        );
+
+        let space = Metadata::space(
+            &metadata.name,
+            &metadata.symbol,
+            &metadata.uri,
+            &metadata.attributes,
+            &metadata.creators,
+        );
+        let rent = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(info.lamports());
+        if rent > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        info.realloc(space, false)?;
+        metadata.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        metadata.emit_update(info.key(), &event_emitter!())?;
+        Ok(())
+    }
+
+    pub fn lock_metadata(ctx: Context<LockMetadata>) -> Result<()> {
+        ctx.accounts.asset.lock()?;
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        Ok(())
+    }
+
+    /// Commits to the final metadata of a hidden collection. Assets are minted with
+    /// placeholder metadata and later revealed against `hash`, see `reveal_leaf`.
+    ///
+    /// The commitment can only be made before the first mint, and never changed.
+    pub fn commit_provenance(ctx: Context<CommitProvenance>, hash: [u8; 32]) -> Result<()> {
+        require!(
+            ctx.accounts.collection.minted == 0,
+            NftStyleOneError::ProvenanceAfterMint
+        );
+        ctx.accounts.provenance.set_inner(Provenance {
+            collection: ctx.accounts.collection.key(),
+            hash,
+            revealed: 0,
         });
+        Ok(())
+    }
 
+    /// Sets the final name and uri of an asset, proven against the collection's provenance hash
+    pub fn reveal(
+        ctx: Context<Reveal>,
+        name: String,
+        uri: String,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.provenance.check_reveal(
+            ctx.accounts.asset.collection_num,
+            &name,
+            &uri,
+            &proof,
+        )?;
+        ctx.accounts.asset.reveal(name, uri)?;
+        ctx.accounts.provenance.record_reveal()?;
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
         Ok(())
     }
 
-    pub fn get_asset_data(ctx: Context<GetAssetDataAccounts>, _data: Vec<u8>) -> Result<()> {
+    /// Verifies the signing creator in the asset's creators list
+    pub fn sign_creator(ctx: Context<SignCreator>) -> Result<()> {
+        ctx.accounts.asset.sign_creator(ctx.accounts.creator.key)?;
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        Ok(())
+    }
+
+    pub fn get_asset_data(
+        ctx: Context<GetAssetDataAccounts>,
+        render_request: Vec<u8>,
+    ) -> Result<()> {
         let data = ctx.accounts.asset_id.try_borrow_mut_data()?;
 
-        let account_disc = &data[0..8];
-        let json_data = if *account_disc == Metadata::DISCRIMINATOR {
-            let metadata_info = Metadata::try_from_slice(&data[8..data.len()])?;
+        let request = RenderRequest::parse(&render_request);
+        match request.page {
+            Some(page) => {
+                let mut document = vec![0u8; MAX_RENDERED_LEN];
+                let len = render_asset(&data, request.format, &mut document)?;
+                set_return_data(&page.response(&document[..len]));
+            }
+            None => {
+                let mut document = [0u8; MAX_RETURN_DATA];
+                let len = render_asset(&data, request.format, &mut document)?;
+                set_return_data(&document[..len]);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accounts every asset instruction needs beyond its interface accounts:
+/// the asset's collection and, unless events are logged, the event CPI accounts
+fn preflight_asset_accounts(
+    asset: &Metadata,
+    program_id: &Pubkey,
+    collection_writable: bool,
+) -> Result<Vec<u8>> {
+    let mut accounts = vec![IAccountMeta {
+        pubkey: asset.collection,
+        signer: false,
+        writable: collection_writable,
+    }];
+    if cfg!(not(feature = "log-events")) {
+        accounts.extend([
+            IAccountMeta {
+                pubkey: Pubkey::find_program_address(&[b"__event_authority"], program_id).0,
+                signer: false,
+                writable: false,
+            },
+            IAccountMeta {
+                pubkey: *program_id,
+                signer: false,
+                writable: false,
+            },
+        ]);
+    }
+
+    Ok(PreflightPayload { accounts }.try_to_vec()?)
+}
 
-            let data = serde_json::to_string(&metadata_info).unwrap();
-            msg!("Found metadata: {}", &data);
-            data
+/// Creates a PDA owned by `owner`, the way Anchor's `init` does.
+///
+/// `create_account` fails on an address that already holds lamports, so anyone could
+/// block a PDA by sending it some. In that case the rent is topped up instead, and the
+/// account is allocated and assigned with the PDA's signature.
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    lamports: u64,
+    space: usize,
+    owner: &Pubkey,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    use anchor_lang::system_program::{
+        allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+    };
+
+    let current = target.lamports();
+    if current == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+                &[seeds],
+            ),
+            lamports,
+            space as u64,
+            owner,
+        );
+    }
+
+    let top_up = lamports.saturating_sub(current);
+    if top_up > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: target.clone(),
+            },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Assign {
+                account_to_assign: target.clone(),
+            },
+            &[seeds],
+        ),
+        owner,
+    )
+}
+
+/// Renders an asset account into `buf` in the requested format, returning the rendered length
+fn render_asset(data: &[u8], format: RenderFormat, buf: &mut [u8]) -> Result<usize> {
+    let account_disc = &data[0..8];
+    if format == RenderFormat::Tlv {
+        let mut writer = TlvWriter::new(buf);
+        if *account_disc == Metadata::DISCRIMINATOR {
+            Metadata::try_from_slice(&data[8..data.len()])?.write_tlv(&mut writer)?;
         } else if *account_disc == Collection::DISCRIMINATOR {
-            let collection_info = Collection::try_from_slice(&data[8..data.len()])?;
-            let data = serde_json::to_string(&collection_info).unwrap();
-            msg!("Found collection: {}", &data);
-            data
-        } else {
-            "".to_string()
-        };
-        anchor_lang::solana_program::program::set_return_data(&json_data.as_bytes());
-        Ok(())
+            Collection::try_from_slice(&data[8..data.len()])?.write_tlv(&mut writer)?;
+        }
+        return Ok(writer.len());
+    }
+
+    let mut writer = JsonWriter::new(buf);
+    if *account_disc == Metadata::DISCRIMINATOR {
+        Metadata::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found metadata: {}", writer.as_str());
+    } else if *account_disc == Collection::DISCRIMINATOR {
+        Collection::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found collection: {}", writer.as_str());
     }
+    Ok(writer.len())
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, NftAsset)]
 #[account]
+#[asset(payload = "tlv_payload", flags = "FLAG_TLV_PAYLOAD")]
 pub struct Metadata {
-    #[serde(with = "serde_pubkey")]
+    #[asset(collection)]
+    collection: Pubkey,
+    collection_num: u32,
+    #[asset(owner)]
+    owner: Pubkey,
+    #[asset(delegate)]
+    delegate: Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    // Fields below were appended after the first release, see `migrate_metadata`
+    /// Cleared by `lock_metadata`, after which name, symbol and uri are frozen
+    is_mutable: bool,
+    attributes: Vec<Attribute>,
+    creators: Vec<Creator>,
+    /// Set by `reveal`, after which the revealed name and uri are final
+    revealed: bool,
+}
+
+/// `Metadata` as first released, before `is_mutable`, attributes and creators
+#[derive(AnchorDeserialize)]
+struct LegacyMetadata {
     collection: Pubkey,
     collection_num: u32,
-    #[serde(with = "serde_pubkey")]
     owner: Pubkey,
-    #[serde(with = "serde_pubkey")]
     delegate: Pubkey,
     name: String,
     symbol: String,
     uri: String,
 }
 
-#[derive(Debug, Serialize)]
-#[account]
-pub struct Collection {
-    #[serde(with = "serde_pubkey")]
-    pub authority: Pubkey,
-    pub num_items: u32,
+impl Metadata {
+    pub fn new(
+        collection: Pubkey,
+        collection_num: u32,
+        owner: Pubkey,
+        item: MintItem,
+    ) -> Result<Self> {
+        Attribute::validate(&item.attributes)?;
+        // The minting authority is the only creator verified up front
+        let creators = new_creators(item.creators, &owner).map_err(NftStyleOneError::from)?;
+        Ok(Metadata {
+            collection,
+            collection_num,
+            owner,
+            delegate: owner,
+            name: item.name,
+            symbol: item.symbol,
+            uri: item.uri,
+            is_mutable: true,
+            attributes: item.attributes,
+            creators,
+            revealed: false,
+        })
+    }
+
+    /// Account size for the given strings, attributes and creators
+    pub fn space(
+        name: &str,
+        symbol: &str,
+        uri: &str,
+        attributes: &[Attribute],
+        creators: &[Creator],
+    ) -> usize {
+        let strings = 4 + name.len() + 4 + symbol.len() + 4 + uri.len();
+        let attributes = 4 + attributes.iter().map(Attribute::space).sum::<usize>();
+        let creators = 4 + creators.len() * CREATOR_LEN;
+        8 + 32 + 4 + 32 + 32 + strings + 1 + attributes + creators + 1
+    }
+
+    /// Reads a metadata account written before `is_mutable`, attributes, creators and
+    /// `revealed` existed.
+    ///
+    /// Those accounts were sized to fit their fields exactly, so one that parses with
+    /// bytes left over is in the current layout.
+    pub fn from_legacy(data: &[u8]) -> Result<Self> {
+        require!(
+            data.starts_with(&Metadata::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let mut body = &data[8..];
+        let legacy = LegacyMetadata::deserialize(&mut body)
+            .map_err(|_| NftStyleOneError::NotLegacyMetadata)?;
+        require!(body.is_empty(), NftStyleOneError::NotLegacyMetadata);
+
+        Ok(Metadata {
+            collection: legacy.collection,
+            collection_num: legacy.collection_num,
+            owner: legacy.owner,
+            delegate: legacy.delegate,
+            name: legacy.name,
+            symbol: legacy.symbol,
+            uri: legacy.uri,
+            is_mutable: true,
+            attributes: vec![],
+            creators: vec![],
+            revealed: false,
+        })
+    }
+
+    /// An asset is delegated while its delegate differs from its owner
+    pub fn is_delegated(&self) -> bool {
+        self.delegate != self.owner
+    }
+
+    /// The owner can always move the asset, and the delegate can while delegated
+    pub fn is_owner_or_delegate(&self, authority: &Pubkey) -> bool {
+        self.owner == *authority || self.delegate == *authority
+    }
+
+    pub fn check_owner_or_delegate(&self, authority: &Pubkey) -> Result<()> {
+        require!(
+            self.is_owner_or_delegate(authority),
+            NftStyleOneError::NotOwnerOrDelegate
+        );
+        Ok(())
+    }
+
+    /// Moves the asset to `destination`. Transfers clear any delegation.
+    pub fn transfer_to(&mut self, destination: Pubkey) {
+        self.owner = destination;
+        self.delegate = destination;
+    }
+
+    pub fn set_delegate(&mut self, delegate: Pubkey) {
+        self.delegate = delegate;
+    }
+
+    pub fn revoke(&mut self) {
+        self.delegate = self.owner;
+    }
+
+    pub fn update(
+        &mut self,
+        name: String,
+        symbol: String,
+        uri: String,
+        attributes: Vec<Attribute>,
+    ) -> Result<()> {
+        require!(self.is_mutable, NftStyleOneError::MetadataImmutable);
+        require!(
+            !self.revealed || (name == self.name && uri == self.uri),
+            NftStyleOneError::MetadataRevealed
+        );
+        Attribute::validate(&attributes)?;
+        self.name = name;
+        self.symbol = symbol;
+        self.uri = uri;
+        self.attributes = attributes;
+        Ok(())
+    }
+
+    /// Until it is revealed, an asset of a collection with a provenance commitment keeps its
+    /// placeholder name and uri, so that they can only be replaced with proven ones.
+    pub fn check_hidden_update(&self, name: &str, uri: &str, committed: bool) -> Result<()> {
+        require!(
+            !committed || self.revealed || (name == self.name && uri == self.uri),
+            NftStyleOneError::MetadataHidden
+        );
+        Ok(())
+    }
+
+    /// Replaces placeholder name and uri with the final ones committed by the collection's provenance.
+    /// An asset is revealed once, and its name and uri cannot be updated afterwards.
+    pub fn reveal(&mut self, name: String, uri: String) -> Result<()> {
+        require!(self.is_mutable, NftStyleOneError::MetadataImmutable);
+        require!(!self.revealed, NftStyleOneError::MetadataRevealed);
+        self.name = name;
+        self.uri = uri;
+        self.revealed = true;
+        Ok(())
+    }
+
+    /// Creators can sign even after the metadata is locked
+    pub fn sign_creator(&mut self, creator: &Pubkey) -> Result<()> {
+        verify_creator(&mut self.creators, creator).map_err(NftStyleOneError::from)?;
+        Ok(())
+    }
+
+    /// Permanently freezes name, symbol and uri
+    pub fn lock(&mut self) -> Result<()> {
+        require!(self.is_mutable, NftStyleOneError::MetadataImmutable);
+        self.is_mutable = false;
+        Ok(())
+    }
+
+    /// Name, symbol, uri, attributes, creators and `revealed` as a TLV map, emitted as the
+    /// AssetGroup payload
+    pub fn tlv_payload(&self) -> Result<Vec<u8>> {
+        let len = tlv::entry_len(tlv::NAME, self.name.as_bytes())
+            + tlv::entry_len(tlv::SYMBOL, self.symbol.as_bytes())
+            + tlv::entry_len(tlv::URI, self.uri.as_bytes())
+            + self
+                .attributes
+                .iter()
+                .map(|attribute| tlv::attribute_entry_len(&attribute.trait_type, &attribute.value))
+                .sum::<usize>()
+            + tlv::entry_len(tlv::CREATORS, &[])
+            + self.creators.len() * CREATOR_LEN
+            + tlv::entry_len(REVEALED, &[0]);
+        let mut payload = vec![0u8; len];
+        let mut writer = TlvWriter::new(&mut payload);
+        writer.write_str(tlv::NAME, &self.name)?;
+        writer.write_str(tlv::SYMBOL, &self.symbol)?;
+        writer.write_str(tlv::URI, &self.uri)?;
+        self.write_tlv_attributes(&mut writer)?;
+        writer.write_creators(&self.creators)?;
+        writer.write(REVEALED, &[self.revealed.into()])?;
+        Ok(payload)
+    }
+
+    fn write_tlv_attributes(&self, writer: &mut TlvWriter) -> Result<()> {
+        for attribute in &self.attributes {
+            writer.write_attribute(&attribute.trait_type, &attribute.value)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_tlv(&self, writer: &mut TlvWriter) -> Result<()> {
+        writer.write_str(tlv::NAME, &self.name)?;
+        writer.write_str(tlv::SYMBOL, &self.symbol)?;
+        writer.write_str(tlv::URI, &self.uri)?;
+        self.write_tlv_attributes(writer)?;
+        writer.write_creators(&self.creators)?;
+        writer.write(REVEALED, &[self.revealed.into()])?;
+        writer.write_pubkey(tlv::OWNER, &self.owner)?;
+        writer.write_pubkey(tlv::DELEGATE, &self.delegate)?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("collection", self.collection)?;
+        writer.field_u64("collection_num", self.collection_num.into())?;
+        writer.field_pubkey("owner", self.owner)?;
+        writer.field_pubkey("delegate", self.delegate)?;
+        writer.field_bool("is_mutable", self.is_mutable)?;
+        writer.field_bool("revealed", self.revealed)?;
+        writer.field_str("name", &self.name)?;
+        writer.field_str("symbol", &self.symbol)?;
+        writer.field_str("uri", &self.uri)?;
+        writer.key("attributes")?;
+        writer.begin_array()?;
+        for attribute in &self.attributes {
+            writer.begin_object()?;
+            writer.field_str("trait_type", &attribute.trait_type)?;
+            writer.field_str("value", &attribute.value)?;
+            writer.end_object()?;
+        }
+        writer.end_array()?;
+        writer.key("creators")?;
+        writer.begin_array()?;
+        for creator in &self.creators {
+            writer.begin_object()?;
+            writer.field_pubkey("address", creator.address)?;
+            writer.field_u64("share_bps", creator.share_bps.into())?;
+            writer.field_bool("verified", creator.verified)?;
+            writer.end_object()?;
+        }
+        writer.end_array()?;
+        writer.end_object()?;
+        Ok(())
+    }
+}
+
+/// A trait of the asset, rendered in the `attributes` array of its JSON
+#[derive(Debug, Clone, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct Attribute {
+    pub trait_type: String,
+    pub value: String,
+}
+
+impl Attribute {
+    pub const MAX_ATTRIBUTES: usize = 16;
+    /// Longest `trait_type` or `value`, in bytes
+    pub const MAX_LEN: usize = 32;
+
+    pub fn space(&self) -> usize {
+        4 + self.trait_type.len() + 4 + self.value.len()
+    }
+
+    pub fn validate(attributes: &[Attribute]) -> Result<()> {
+        require!(
+            attributes.len() <= Self::MAX_ATTRIBUTES,
+            NftStyleOneError::TooManyAttributes
+        );
+        for attribute in attributes {
+            require!(
+                attribute.trait_type.len() <= Self::MAX_LEN
+                    && attribute.value.len() <= Self::MAX_LEN,
+                NftStyleOneError::AttributeTooLong
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, NftAsset)]
+#[account]
+#[asset(payload = "tlv_payload", flags = "FLAG_TLV_PAYLOAD")]
+pub struct Collection {
+    #[asset(owner)]
+    pub authority: Pubkey,
+    pub num_items: u32,
+    /// Assets minted and not yet burned
+    pub supply: u32,
+    /// Assets ever minted, and the next number `mint_next` uses
+    pub minted: u32,
+    /// Proposed by the authority, becomes the authority once it accepts
+    pub pending_authority: Option<Pubkey>,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl Collection {
+    /// Length of the first released layout, `{ authority, num_items }`
+    const LEGACY_LEN: usize = 8 + 32 + 4;
+
+    pub fn space(&self) -> usize {
+        8 + 32 + 4 + 4 + 4 + 33 + 4 + self.name.len() + 4 + self.symbol.len() + 4 + self.uri.len()
+    }
+
+    /// Metadata given to publicly minted assets: the collection's name numbered after the
+    /// asset, with its symbol and uri. Hidden drops replace it later with `reveal`.
+    pub fn placeholder(&self, collection_num: u32) -> MintItem {
+        MintItem {
+            name: format!("{} #{}", self.name, collection_num),
+            symbol: self.symbol.clone(),
+            uri: self.uri.clone(),
+            attributes: vec![],
+            creators: vec![],
+        }
+    }
+
+    /// Reads a collection stored in the first released layout. Its counters start at zero
+    /// until `set_counts` fills them in.
+    pub fn from_legacy(data: &[u8]) -> Result<Self> {
+        require!(
+            data.starts_with(&Collection::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(
+            data.len() == Self::LEGACY_LEN,
+            NftStyleOneError::NotLegacyCollection
+        );
+
+        Ok(Collection {
+            authority: Pubkey::new_from_array(data[8..40].try_into().unwrap()),
+            num_items: u32::from_le_bytes(data[40..44].try_into().unwrap()),
+            supply: 0,
+            minted: 0,
+            pending_authority: None,
+            name: String::new(),
+            symbol: String::new(),
+            uri: String::new(),
+        })
+    }
+
+    /// Sets the counters of a migrated collection. Live assets can't outnumber minted ones,
+    /// and no more than `num_items` can be minted.
+    pub fn set_counts(&mut self, supply: u32, minted: u32) -> Result<()> {
+        require!(
+            supply <= minted && minted <= self.num_items,
+            NftStyleOneError::InvalidCollectionCounts
+        );
+        self.supply = supply;
+        self.minted = minted;
+        Ok(())
+    }
+
+    /// Completes a handoff started by `propose_authority`
+    pub fn accept_authority(&mut self, new_authority: &Pubkey) -> Result<()> {
+        require!(
+            self.pending_authority == Some(*new_authority),
+            NftStyleOneError::NotPendingAuthority
+        );
+        self.authority = *new_authority;
+        self.pending_authority = None;
+        Ok(())
+    }
+
+    /// Only the collection authority can mint, and only numbers below `num_items`
+    pub fn check_mint(&self, minter: &Pubkey, start_num: u32, count: u32) -> Result<()> {
+        require_keys_eq!(*minter, self.authority, NftStyleOneError::UnauthorizedMint);
+        self.check_range(start_num, count)
+    }
+
+    /// Numbers `start_num..start_num + count` all fall below `num_items`
+    pub fn check_range(&self, start_num: u32, count: u32) -> Result<()> {
+        let end = start_num
+            .checked_add(count)
+            .ok_or(NftStyleOneError::CollectionNumOutOfRange)?;
+        require!(
+            end <= self.num_items,
+            NftStyleOneError::CollectionNumOutOfRange
+        );
+        Ok(())
+    }
+
+    pub fn record_mint(&mut self, count: u32) -> Result<()> {
+        self.minted = self
+            .minted
+            .checked_add(count)
+            .ok_or(NftStyleOneError::SupplyOverflow)?;
+        self.supply = self
+            .supply
+            .checked_add(count)
+            .ok_or(NftStyleOneError::SupplyOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_burn(&mut self) -> Result<()> {
+        self.supply = self
+            .supply
+            .checked_sub(1)
+            .ok_or(NftStyleOneError::SupplyUnderflow)?;
+        Ok(())
+    }
+
+    /// Name, symbol and uri as a TLV map, emitted as the AssetGroup payload
+    pub fn tlv_payload(&self) -> Result<Vec<u8>> {
+        Ok(encode_tlv(&[
+            (tlv::NAME, self.name.as_bytes()),
+            (tlv::SYMBOL, self.symbol.as_bytes()),
+            (tlv::URI, self.uri.as_bytes()),
+        ])?)
+    }
+
+    pub fn to_collection_asset(&self, asset_id: Pubkey) -> Result<NftCollectionAsset> {
+        Ok(NftCollectionAsset {
+            asset_id,
+            authority: self.authority,
+            pubkeys: vec![asset_id],
+            data: self.tlv_payload()?,
+        })
+    }
+
+    pub fn write_tlv(&self, writer: &mut TlvWriter) -> Result<()> {
+        writer.write_str(tlv::NAME, &self.name)?;
+        writer.write_str(tlv::SYMBOL, &self.symbol)?;
+        writer.write_str(tlv::URI, &self.uri)?;
+        writer.write_pubkey(tlv::AUTHORITY, &self.authority)?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("authority", self.authority)?;
+        writer.key("pending_authority")?;
+        match self.pending_authority {
+            Some(pending_authority) => writer.pubkey(pending_authority)?,
+            None => writer.null()?,
+        }
+        writer.field_u64("num_items", self.num_items.into())?;
+        writer.field_u64("supply", self.supply.into())?;
+        writer.field_u64("minted", self.minted.into())?;
+        writer.field_str("name", &self.name)?;
+        writer.field_str("symbol", &self.symbol)?;
+        writer.field_str("uri", &self.uri)?;
+        writer.end_object()?;
+        Ok(())
+    }
+}
+
+/// Price, schedule and allowlist of a collection's `public_mint`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintConfigArgs {
+    /// Lamports paid to `recipient` for each mint
+    pub price: u64,
+    pub recipient: Pubkey,
+    /// First slot minting is open
+    pub start_slot: u64,
+    /// First slot minting is closed again
+    pub end_slot: u64,
+    /// Most assets a single wallet can public mint
+    pub wallet_limit: Option<u32>,
+    /// Merkle root of the allowed wallets, see `verify_allowlist`
+    pub allowlist_root: Option<[u8; 32]>,
+}
+
+#[derive(Debug)]
+#[account]
+pub struct MintConfig {
+    pub collection: Pubkey,
+    pub price: u64,
+    pub recipient: Pubkey,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub wallet_limit: Option<u32>,
+    pub allowlist_root: Option<[u8; 32]>,
+}
+
+impl MintConfig {
+    pub const LEN: usize = 8 + 32 + 8 + 32 + 8 + 8 + 5 + 33;
+
+    pub fn new(collection: Pubkey, args: MintConfigArgs) -> Result<Self> {
+        require!(
+            args.start_slot < args.end_slot,
+            NftStyleOneError::InvalidMintWindow
+        );
+        Ok(MintConfig {
+            collection,
+            price: args.price,
+            recipient: args.recipient,
+            start_slot: args.start_slot,
+            end_slot: args.end_slot,
+            wallet_limit: args.wallet_limit,
+            allowlist_root: args.allowlist_root,
+        })
+    }
+
+    /// `minter`, having already minted `wallet_minted` assets, can mint one more at `slot`
+    pub fn check_public_mint(
+        &self,
+        minter: &Pubkey,
+        slot: u64,
+        wallet_minted: u32,
+        proof: &[[u8; 32]],
+    ) -> Result<()> {
+        require!(slot >= self.start_slot, NftStyleOneError::MintNotStarted);
+        require!(slot < self.end_slot, NftStyleOneError::MintEnded);
+        if let Some(wallet_limit) = self.wallet_limit {
+            require!(
+                wallet_minted < wallet_limit,
+                NftStyleOneError::WalletLimitReached
+            );
+        }
+        if let Some(root) = self.allowlist_root {
+            require!(
+                verify_allowlist(&root, minter, proof),
+                NftStyleOneError::NotAllowlisted
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Assets a wallet has minted through `public_mint`
+#[account]
+pub struct WalletMints {
+    pub minted: u32,
+}
+
+impl WalletMints {
+    pub const LEN: usize = 8 + 4;
+
+    pub fn record_mint(&mut self) -> Result<()> {
+        self.minted = self
+            .minted
+            .checked_add(1)
+            .ok_or(NftStyleOneError::SupplyOverflow)?;
+        Ok(())
+    }
+}
+
+/// Checks `proof` against an allowlist merkle tree, whose leaves are `sha256(wallet)`
+pub fn verify_allowlist(root: &[u8; 32], wallet: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    verify_merkle_proof(root, hashv(&[wallet.as_ref()]).to_bytes(), proof)
+}
+
+/// Each node is the sha256 of its two children, smaller one first, so proofs need no
+/// left/right flags
+fn verify_merkle_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = if node <= *sibling {
+            hashv(&[&node, sibling])
+        } else {
+            hashv(&[sibling, &node])
+        }
+        .to_bytes();
+    }
+    node == *root
+}
+
+/// Hidden-metadata commitment of a collection, made before anything is minted
+#[account]
+pub struct Provenance {
+    pub collection: Pubkey,
+    /// Merkle root of the final metadata, with leaves from `reveal_leaf`
+    pub hash: [u8; 32],
+    /// Assets revealed so far
+    pub revealed: u32,
+}
+
+impl Provenance {
+    pub const LEN: usize = 8 + 32 + 32 + 4;
+
+    /// `name` and `uri` are the committed final metadata of `collection_num`
+    pub fn check_reveal(
+        &self,
+        collection_num: u32,
+        name: &str,
+        uri: &str,
+        proof: &[[u8; 32]],
+    ) -> Result<()> {
+        require!(
+            verify_merkle_proof(&self.hash, reveal_leaf(collection_num, name, uri), proof),
+            NftStyleOneError::InvalidRevealProof
+        );
+        Ok(())
+    }
+
+    pub fn record_reveal(&mut self) -> Result<()> {
+        self.revealed = self
+            .revealed
+            .checked_add(1)
+            .ok_or(NftStyleOneError::SupplyOverflow)?;
+        Ok(())
+    }
+}
+
+/// `sha256(collection_num LE || name length as u32 LE || name || uri)`, a leaf of the provenance tree
+pub fn reveal_leaf(collection_num: u32, name: &str, uri: &str) -> [u8; 32] {
+    hashv(&[
+        &collection_num.to_le_bytes(),
+        &(name.len() as u32).to_le_bytes(),
+        name.as_bytes(),
+        uri.as_bytes(),
+    ])
+    .to_bytes()
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+#[instruction(num_items: u32, name: String, symbol: String, uri: String)]
+pub struct InitCollection<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(init, payer=owner, space = 8 + 32 + 4 + 4 + 4 + 33 + 4 + name.len() + 4 + symbol.len() + 4 + uri.len())]
+    pub collection: Account<'info, Collection>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct MigrateCollection<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: a `Collection` in a legacy layout, checked by `Collection::from_legacy`
+    #[account(mut, owner = crate::ID)]
+    pub collection: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct MigrateMetadata<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: a `Metadata` in the first released layout, checked by `Metadata::from_legacy`
+    #[account(mut, owner = crate::ID)]
+    pub asset: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority @ NftStyleOneError::UnauthorizedAuthority)]
+    pub collection: Account<'info, Collection>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+    #[account(mut)]
+    pub collection: Account<'info, Collection>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+#[instruction(collection_num: u32, name: String, symbol: String, uri: String, attributes: Vec<Attribute>, creators: Vec<Creator>)]
+pub struct MintMe<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub collection: Account<'info, Collection>,
+    #[account(init, payer=owner, space = Metadata::space(&name, &symbol, &uri, &attributes, &creators), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+#[instruction(name: String, symbol: String, uri: String, attributes: Vec<Attribute>, creators: Vec<Creator>)]
+pub struct MintNext<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub collection: Account<'info, Collection>,
+    #[account(init, payer=owner, space = Metadata::space(&name, &symbol, &uri, &attributes, &creators), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection.minted.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintItem {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub attributes: Vec<Attribute>,
+    pub creators: Vec<Creator>,
+}
+
+impl MintItem {
+    /// Size of the metadata account minted from this item
+    pub fn space(&self) -> usize {
+        Metadata::space(
+            &self.name,
+            &self.symbol,
+            &self.uri,
+            &self.attributes,
+            &self.creators,
+        )
+    }
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
-pub struct InitCollection<'info> {
+pub struct MintBatch<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
-    #[account(init, payer=owner, space = 8 + 32 + 4)]
+    #[account(mut)]
     pub collection: Account<'info, Collection>,
     pub system_program: Program<'info, System>,
 }
 
-#[event_cpi]
 #[derive(Accounts)]
-#[instruction(collection_num: u32, name: String, symbol: String, uri: String)]
-pub struct MintMe<'info> {
+pub struct InitMintConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ NftStyleOneError::UnauthorizedAuthority)]
+    pub collection: Account<'info, Collection>,
+    #[account(init, payer = authority, space = MintConfig::LEN, seeds = [collection.key().as_ref(), b"mint_config".as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMintConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ NftStyleOneError::UnauthorizedAuthority)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"mint_config".as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct PublicMint<'info> {
+    #[account(mut)]
+    pub minter: Signer<'info>,
     #[account(mut)]
-    pub owner: Signer<'info>,
     pub collection: Account<'info, Collection>,
-    #[account(init, payer=owner, space = 8 + 32 + 4 + 32 + 32 + 4 + name.len() + 4 + symbol.len() + 4 + uri.len(), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
+    #[account(has_one = collection, has_one = recipient, seeds = [collection.key().as_ref(), b"mint_config".as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    /// CHECK: checked against `mint_config.recipient`
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    #[account(init_if_needed, payer = minter, space = WalletMints::LEN, seeds = [mint_config.key().as_ref(), b"wallet".as_ref(), minter.key().as_ref()], bump)]
+    pub wallet_mints: Account<'info, WalletMints>,
+    #[account(init, payer = minter, space = collection.placeholder(collection.minted).space(), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection.minted.to_le_bytes()], bump)]
     pub asset: Account<'info, Metadata>,
     pub system_program: Program<'info, System>,
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
 pub struct TransferMe<'info> {
-    /// CHECK:
+    /// CHECK: must be the asset's current owner
     pub owner: AccountInfo<'info>,
     /// CHECK: recipient
     pub destination: AccountInfo<'info>,
     pub authority: Signer<'info>,
-    #[account(mut, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    #[account(mut, has_one = owner @ NftStyleOneError::OwnerMismatch, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub collection: Account<'info, Collection>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct DelegateMe<'info> {
+    pub owner: Signer<'info>,
+    /// CHECK: any account can be a delegate
+    pub delegate: AccountInfo<'info>,
+    #[account(mut, has_one = owner, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub collection: Account<'info, Collection>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct RevokeMe<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, has_one = owner, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub collection: Account<'info, Collection>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct BurnMe<'info> {
+    pub authority: Signer<'info>,
+    /// CHECK: receives the asset's rent
+    #[account(mut)]
+    pub rent_recipient: AccountInfo<'info>,
+    #[account(mut, close = rent_recipient, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    #[account(mut)]
+    pub collection: Account<'info, Collection>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+#[instruction(name: String, symbol: String, uri: String, attributes: Vec<Attribute>)]
+pub struct UpdateMetadata<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ NftStyleOneError::UnauthorizedUpdate)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump, realloc = Metadata::space(&name, &symbol, &uri, &attributes, &asset.creators), realloc::payer = authority, realloc::zero = false)]
+    pub asset: Account<'info, Metadata>,
+    /// CHECK: the collection's provenance PDA, owned by this program once committed
+    #[account(seeds = [collection.key().as_ref(), b"provenance".as_ref()], bump)]
+    pub provenance: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct LockMetadata<'info> {
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ NftStyleOneError::UnauthorizedUpdate)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+}
+
+#[derive(Accounts)]
+pub struct CommitProvenance<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ NftStyleOneError::UnauthorizedAuthority)]
+    pub collection: Account<'info, Collection>,
+    #[account(init, payer = authority, space = Provenance::LEN, seeds = [collection.key().as_ref(), b"provenance".as_ref()], bump)]
+    pub provenance: Account<'info, Provenance>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+#[instruction(name: String, uri: String)]
+pub struct Reveal<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority @ NftStyleOneError::UnauthorizedUpdate)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"provenance".as_ref()], bump)]
+    pub provenance: Account<'info, Provenance>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump, realloc = Metadata::space(&name, &asset.symbol, &uri, &asset.attributes, &asset.creators), realloc::payer = authority, realloc::zero = false)]
     pub asset: Account<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct SignCreator<'info> {
+    pub creator: Signer<'info>,
     pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
 }
 
 #[derive(Accounts)]
@@ -221,6 +1619,30 @@ pub struct CudDelete {
     asset_id: Pubkey,
 }
 
+#[event]
+pub struct NftTransferred {
+    asset: Pubkey,
+    from: Pubkey,
+    to: Pubkey,
+    authority: Pubkey,
+}
+
+#[event]
+pub struct NftDelegated {
+    asset: Pubkey,
+    owner: Pubkey,
+    delegate: Pubkey,
+    authority: Pubkey,
+}
+
+#[event]
+pub struct NftRevoked {
+    asset: Pubkey,
+    owner: Pubkey,
+    delegate: Pubkey,
+    authority: Pubkey,
+}
+
 // This is a copy-paste from `additional-accounts-request` crate, needed
 // to make sure that we can deserialize the return data in
 // our typescript client
@@ -243,3 +1665,763 @@ pub struct ITransfer<'info> {
     /// CHECK:
     pub asset: Account<'info, Metadata>,
 }
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct IDelegate<'info> {
+    /// CHECK:
+    pub owner: AccountInfo<'info>,
+    /// CHECK:
+    pub delegate: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct IRevoke<'info> {
+    /// CHECK:
+    pub owner: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct IBurn<'info> {
+    /// CHECK:
+    pub authority: AccountInfo<'info>,
+    /// CHECK:
+    pub rent_recipient: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+#[error_code]
+pub enum NftStyleOneError {
+    #[msg("Signer is neither the owner nor the delegate of the asset")]
+    NotOwnerOrDelegate,
+    #[msg("Collection supply would overflow")]
+    SupplyOverflow,
+    #[msg("Collection supply would underflow")]
+    SupplyUnderflow,
+    #[msg("Only the collection authority can mint")]
+    UnauthorizedMint,
+    #[msg("Collection number must be below the collection's num_items")]
+    CollectionNumOutOfRange,
+    #[msg("Only the collection authority can update metadata")]
+    UnauthorizedUpdate,
+    #[msg("Metadata is locked")]
+    MetadataImmutable,
+    #[msg("Only the collection authority can do this")]
+    UnauthorizedAuthority,
+    #[msg("Signer is not the pending collection authority")]
+    NotPendingAuthority,
+    #[msg("Collection is not in a legacy layout")]
+    NotLegacyCollection,
+    #[msg("Owner account does not match the asset's owner")]
+    OwnerMismatch,
+    #[msg("Metadata can hold at most 16 attributes")]
+    TooManyAttributes,
+    #[msg("Attribute trait types and values are at most 32 bytes")]
+    AttributeTooLong,
+    #[msg("Metadata can list at most 5 creators")]
+    TooManyCreators,
+    #[msg("Creator shares must add up to 10000 basis points")]
+    InvalidCreatorShares,
+    #[msg("A creator is listed more than once")]
+    DuplicateCreator,
+    #[msg("Signer is not a creator of the asset")]
+    UnknownCreator,
+    #[msg("Mint start slot must be before its end slot")]
+    InvalidMintWindow,
+    #[msg("Public mint has not started")]
+    MintNotStarted,
+    #[msg("Public mint has ended")]
+    MintEnded,
+    #[msg("Wallet has reached its public mint limit")]
+    WalletLimitReached,
+    #[msg("Wallet is not on the allowlist")]
+    NotAllowlisted,
+    #[msg("Provenance must be committed before the first mint")]
+    ProvenanceAfterMint,
+    #[msg("Revealed metadata does not match the provenance hash")]
+    InvalidRevealProof,
+    #[msg("Metadata is not in the first released layout")]
+    NotLegacyMetadata,
+    #[msg("Revealed name and uri are final")]
+    MetadataRevealed,
+    #[msg("Supply can't exceed minted, which can't exceed num_items")]
+    InvalidCollectionCounts,
+    #[msg("Hidden name and uri can only be changed by reveal")]
+    MetadataHidden,
+}
+
+impl From<CreatorError> for NftStyleOneError {
+    fn from(e: CreatorError) -> Self {
+        match e {
+            CreatorError::TooManyCreators => NftStyleOneError::TooManyCreators,
+            CreatorError::InvalidShares => NftStyleOneError::InvalidCreatorShares,
+            CreatorError::DuplicateCreator => NftStyleOneError::DuplicateCreator,
+            CreatorError::UnknownCreator => NftStyleOneError::UnknownCreator,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(owner: Pubkey) -> Metadata {
+        Metadata::new(Pubkey::new_unique(), 0, owner, mint_item(vec![])).unwrap()
+    }
+
+    fn mint_item(creators: Vec<Creator>) -> MintItem {
+        MintItem {
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+            attributes: vec![],
+            creators,
+        }
+    }
+
+    fn attribute(trait_type: &str, value: &str) -> Attribute {
+        Attribute {
+            trait_type: trait_type.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn collection(authority: Pubkey, num_items: u32) -> Collection {
+        Collection {
+            authority,
+            num_items,
+            supply: 0,
+            minted: 0,
+            pending_authority: None,
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+        }
+    }
+
+    #[test]
+    fn public_mints_get_placeholder_metadata() {
+        let minter = Pubkey::new_unique();
+        let item = collection(Pubkey::new_unique(), 10).placeholder(7);
+        let space = item.space();
+        let metadata = Metadata::new(Pubkey::new_unique(), 7, minter, item).unwrap();
+
+        assert_eq!(
+            (
+                metadata.name.as_str(),
+                metadata.symbol.as_str(),
+                metadata.uri.as_str()
+            ),
+            ("name #7", "symbol", "uri")
+        );
+        assert!(metadata.attributes.is_empty() && metadata.creators.is_empty());
+        assert_eq!(metadata.try_to_vec().unwrap().len() + 8, space);
+    }
+
+    #[test]
+    fn preflight_skips_event_accounts_when_logging() {
+        let asset = metadata(Pubkey::new_unique());
+        let payload = preflight_asset_accounts(&asset, &crate::ID, true).unwrap();
+        let accounts = PreflightPayload::try_from_slice(&payload).unwrap().accounts;
+
+        assert_eq!(accounts[0].pubkey, asset.collection);
+        assert!(accounts[0].writable);
+        if cfg!(feature = "log-events") {
+            assert_eq!(accounts.len(), 1);
+        } else {
+            assert_eq!(accounts.len(), 3);
+            assert_eq!(accounts[2].pubkey, crate::ID);
+        }
+    }
+
+    #[test]
+    fn authority_handoff_needs_acceptance() {
+        let authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let mut collection = collection(authority, 10);
+
+        assert_eq!(
+            collection.accept_authority(&new_authority),
+            Err(NftStyleOneError::NotPendingAuthority.into())
+        );
+        collection.pending_authority = Some(new_authority);
+        assert_eq!(
+            collection.accept_authority(&Pubkey::new_unique()),
+            Err(NftStyleOneError::NotPendingAuthority.into())
+        );
+        assert_eq!(collection.authority, authority);
+
+        collection.accept_authority(&new_authority).unwrap();
+        assert_eq!(collection.authority, new_authority);
+        assert_eq!(collection.pending_authority, None);
+    }
+
+    #[test]
+    fn migrates_legacy_collections() {
+        let authority = Pubkey::new_unique();
+        let mut data = Collection::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(&10u32.to_le_bytes());
+
+        let mut migrated = Collection::from_legacy(&data).unwrap();
+        assert_eq!(migrated.authority, authority);
+        assert_eq!(migrated.num_items, 10);
+
+        for (supply, minted) in [(5, 4), (4, 11)] {
+            assert_eq!(
+                migrated.set_counts(supply, minted),
+                Err(NftStyleOneError::InvalidCollectionCounts.into())
+            );
+        }
+        migrated.set_counts(3, 4).unwrap();
+        assert_eq!((migrated.supply, migrated.minted), (3, 4));
+        // Assets minted before the upgrade can be burned, and numbering continues
+        migrated.record_burn().unwrap();
+        migrated.record_mint(1).unwrap();
+        assert_eq!((migrated.supply, migrated.minted), (3, 5));
+
+        migrated.name = "name".to_string();
+        migrated.pending_authority = Some(authority);
+        let mut current = Vec::new();
+        migrated.try_serialize(&mut current).unwrap();
+        assert_eq!(current.len(), migrated.space());
+        assert_eq!(
+            Collection::from_legacy(&current).unwrap_err(),
+            NftStyleOneError::NotLegacyCollection.into()
+        );
+        assert_eq!(
+            Collection::from_legacy(&[0; 8 + 32 + 4]).unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+
+    #[test]
+    fn migrates_legacy_metadata() {
+        let (collection, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // The first released layout, sized exactly like its `init`
+        let mut data = Metadata::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(collection.as_ref());
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.extend_from_slice(owner.as_ref());
+        data.extend_from_slice(owner.as_ref());
+        for field in ["name", "symbol", "uri"] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        assert_eq!(data.len(), 8 + 32 + 4 + 32 + 32 + 4 + 4 + 4 + 6 + 4 + 3);
+
+        let migrated = Metadata::from_legacy(&data).unwrap();
+        assert_eq!(migrated.collection, collection);
+        assert_eq!(migrated.collection_num, 7);
+        assert_eq!((migrated.owner, migrated.delegate), (owner, owner));
+        assert_eq!(
+            (
+                migrated.name.as_str(),
+                migrated.symbol.as_str(),
+                migrated.uri.as_str()
+            ),
+            ("name", "symbol", "uri")
+        );
+        assert!(migrated.is_mutable);
+        assert!(migrated.attributes.is_empty() && migrated.creators.is_empty());
+
+        // The old fields are a prefix of the new layout
+        let mut current = Vec::new();
+        migrated.try_serialize(&mut current).unwrap();
+        assert_eq!(current[..data.len()], data[..]);
+        assert_eq!(
+            current.len(),
+            Metadata::space("name", "symbol", "uri", &[], &[])
+        );
+        assert_eq!(
+            Metadata::from_legacy(&current).unwrap_err(),
+            NftStyleOneError::NotLegacyMetadata.into()
+        );
+        assert_eq!(
+            Metadata::from_legacy(&data[..data.len() - 1]).unwrap_err(),
+            NftStyleOneError::NotLegacyMetadata.into()
+        );
+        data[0] ^= 1;
+        assert_eq!(
+            Metadata::from_legacy(&data).unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+
+    #[test]
+    fn authority_can_mint_in_range() {
+        let authority = Pubkey::new_unique();
+        let mut collection = collection(authority, 10);
+
+        collection.check_mint(&authority, 0, 1).unwrap();
+        collection.check_mint(&authority, 9, 1).unwrap();
+        collection.check_mint(&authority, 0, 10).unwrap();
+
+        collection.record_mint(3).unwrap();
+        assert_eq!(collection.minted, 3);
+        assert_eq!(collection.supply, 3);
+        collection.record_burn().unwrap();
+        assert_eq!(collection.minted, 3);
+        assert_eq!(collection.supply, 2);
+    }
+
+    #[test]
+    fn unauthorized_mint_fails() {
+        let collection = collection(Pubkey::new_unique(), 10);
+        assert_eq!(
+            collection.check_mint(&Pubkey::new_unique(), 0, 1),
+            Err(NftStyleOneError::UnauthorizedMint.into())
+        );
+    }
+
+    #[test]
+    fn out_of_range_mint_fails() {
+        let authority = Pubkey::new_unique();
+        let collection = collection(authority, 10);
+        for (start_num, count) in [(10, 1), (9, 2), (0, 11), (u32::MAX, 1)] {
+            assert_eq!(
+                collection.check_mint(&authority, start_num, count),
+                Err(NftStyleOneError::CollectionNumOutOfRange.into())
+            );
+        }
+    }
+
+    #[test]
+    fn burn_without_supply_fails() {
+        let mut collection = collection(Pubkey::new_unique(), 10);
+        assert_eq!(
+            collection.record_burn(),
+            Err(NftStyleOneError::SupplyUnderflow.into())
+        );
+    }
+
+    #[test]
+    fn locked_metadata_cannot_change() {
+        let mut metadata = metadata(Pubkey::new_unique());
+        metadata
+            .update(
+                "a longer name".to_string(),
+                "S".to_string(),
+                "".to_string(),
+                vec![],
+            )
+            .unwrap();
+        assert_eq!(metadata.name, "a longer name");
+        assert_eq!(metadata.uri, "");
+
+        metadata.lock().unwrap();
+        assert!(!metadata.is_mutable);
+        assert_eq!(
+            metadata.update(
+                "name".to_string(),
+                "symbol".to_string(),
+                "uri".to_string(),
+                vec![]
+            ),
+            Err(NftStyleOneError::MetadataImmutable.into())
+        );
+        assert_eq!(
+            metadata.lock(),
+            Err(NftStyleOneError::MetadataImmutable.into())
+        );
+        assert_eq!(metadata.name, "a longer name");
+    }
+
+    #[test]
+    fn only_owner_or_delegate_can_transfer() {
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let mut metadata = metadata(owner);
+
+        metadata.check_owner_or_delegate(&owner).unwrap();
+        assert_eq!(
+            metadata.check_owner_or_delegate(&delegate),
+            Err(NftStyleOneError::NotOwnerOrDelegate.into())
+        );
+
+        metadata.set_delegate(delegate);
+        metadata.check_owner_or_delegate(&owner).unwrap();
+        metadata.check_owner_or_delegate(&delegate).unwrap();
+        assert_eq!(
+            metadata.check_owner_or_delegate(&Pubkey::new_unique()),
+            Err(NftStyleOneError::NotOwnerOrDelegate.into())
+        );
+
+        // The previous owner and delegate lose control once the asset moves
+        let destination = Pubkey::new_unique();
+        metadata.transfer_to(destination);
+        for stale in [owner, delegate] {
+            assert_eq!(
+                metadata.check_owner_or_delegate(&stale),
+                Err(NftStyleOneError::NotOwnerOrDelegate.into())
+            );
+        }
+        metadata.check_owner_or_delegate(&destination).unwrap();
+    }
+
+    #[test]
+    fn attributes_are_bounded() {
+        let mut metadata = metadata(Pubkey::new_unique());
+        let too_many = vec![attribute("trait", "value"); Attribute::MAX_ATTRIBUTES + 1];
+        let too_long = "x".repeat(Attribute::MAX_LEN + 1);
+        for (attributes, error) in [
+            (too_many, NftStyleOneError::TooManyAttributes),
+            (
+                vec![attribute(&too_long, "value")],
+                NftStyleOneError::AttributeTooLong,
+            ),
+            (
+                vec![attribute("trait", &too_long)],
+                NftStyleOneError::AttributeTooLong,
+            ),
+        ] {
+            assert_eq!(
+                metadata.update(
+                    "name".to_string(),
+                    "symbol".to_string(),
+                    "uri".to_string(),
+                    attributes
+                ),
+                Err(error.into())
+            );
+        }
+        assert!(metadata.attributes.is_empty());
+    }
+
+    #[test]
+    fn attributes_are_rendered_and_emitted() {
+        let mut metadata = metadata(Pubkey::new_unique());
+        let attributes = vec![attribute("Background", "Blue"), attribute("Eyes", "Laser")];
+        metadata
+            .update(
+                "name".to_string(),
+                "symbol".to_string(),
+                "uri".to_string(),
+                attributes.clone(),
+            )
+            .unwrap();
+
+        let mut account = Vec::new();
+        metadata.try_serialize(&mut account).unwrap();
+        assert_eq!(
+            account.len(),
+            Metadata::space("name", "symbol", "uri", &attributes, &[])
+        );
+
+        let payload = metadata.tlv_payload().unwrap();
+        let traits: Vec<_> = tlv::TlvReader::new(&payload)
+            .filter_map(|entry| {
+                let entry = entry.unwrap();
+                Some((entry.attribute_trait()?, entry.value_str()?))
+            })
+            .collect();
+        assert_eq!(traits, [("Background", "Blue"), ("Eyes", "Laser")]);
+
+        let mut buf = [0u8; 512];
+        let mut writer = JsonWriter::new(&mut buf);
+        metadata.write_json(&mut writer).unwrap();
+        assert!(writer.as_str().ends_with(
+            r#""attributes":[{"trait_type":"Background","value":"Blue"},{"trait_type":"Eyes","value":"Laser"}],"creators":[]}"#
+        ));
+    }
+
+    #[test]
+    fn creators_sign_for_themselves() {
+        let owner = Pubkey::new_unique();
+        let artist = Pubkey::new_unique();
+        let creator = |address, share_bps| Creator {
+            address,
+            share_bps,
+            verified: true,
+        };
+
+        let mint = |creators| Metadata::new(Pubkey::new_unique(), 0, owner, mint_item(creators));
+        assert_eq!(
+            mint(vec![creator(owner, 5_000), creator(artist, 4_000)]).unwrap_err(),
+            NftStyleOneError::InvalidCreatorShares.into()
+        );
+
+        let mut metadata = mint(vec![creator(owner, 2_500), creator(artist, 7_500)]).unwrap();
+        assert_eq!(
+            metadata
+                .creators
+                .iter()
+                .map(|c| c.verified)
+                .collect::<Vec<_>>(),
+            [true, false]
+        );
+        assert_eq!(
+            metadata.sign_creator(&Pubkey::new_unique()),
+            Err(NftStyleOneError::UnknownCreator.into())
+        );
+        metadata.sign_creator(&artist).unwrap();
+        assert!(metadata.creators[1].verified);
+
+        let mut account = Vec::new();
+        metadata.try_serialize(&mut account).unwrap();
+        assert_eq!(
+            account.len(),
+            Metadata::space("name", "symbol", "uri", &[], &metadata.creators)
+        );
+        let payload = metadata.tlv_payload().unwrap();
+        let creators = tlv::TlvReader::new(&payload)
+            .get(tlv::CREATORS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            nft_events::decode_creators(creators.value).unwrap(),
+            metadata.creators
+        );
+    }
+
+    #[test]
+    fn delegate_can_transfer() {
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let mut asset = metadata(owner);
+        assert!(!asset.is_delegated());
+        assert!(!asset.is_owner_or_delegate(&delegate));
+
+        asset.set_delegate(delegate);
+        assert!(asset.is_delegated());
+        assert!(asset.is_owner_or_delegate(&owner));
+        assert!(asset.is_owner_or_delegate(&delegate));
+        assert!(!asset.is_owner_or_delegate(&stranger));
+
+        let group = asset.to_asset_group(Pubkey::new_unique()).unwrap();
+        assert_eq!(group.pubkeys, vec![asset.collection, delegate]);
+        assert_eq!(group.authority, owner);
+    }
+
+    #[test]
+    fn transfer_clears_delegate() {
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let mut asset = metadata(owner);
+        asset.set_delegate(delegate);
+
+        asset.transfer_to(destination);
+        assert!(!asset.is_delegated());
+        assert!(!asset.is_owner_or_delegate(&owner));
+        assert!(!asset.is_owner_or_delegate(&delegate));
+
+        let group = asset.to_asset_group(Pubkey::new_unique()).unwrap();
+        assert_eq!(group.pubkeys, vec![asset.collection, destination]);
+        assert_eq!(group.authority, destination);
+    }
+
+    #[test]
+    fn revoke_restores_owner() {
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let mut asset = metadata(owner);
+        asset.set_delegate(delegate);
+
+        asset.revoke();
+        assert!(!asset.is_delegated());
+        assert!(!asset.is_owner_or_delegate(&delegate));
+
+        let group = asset.to_asset_group(Pubkey::new_unique()).unwrap();
+        assert_eq!(group.pubkeys, vec![asset.collection, owner]);
+    }
+
+    fn mint_config_args(start_slot: u64, end_slot: u64) -> MintConfigArgs {
+        MintConfigArgs {
+            price: 1_000,
+            recipient: Pubkey::new_unique(),
+            start_slot,
+            end_slot,
+            wallet_limit: None,
+            allowlist_root: None,
+        }
+    }
+
+    fn allowlist_node(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        if a <= b {
+            hashv(&[&a, &b]).to_bytes()
+        } else {
+            hashv(&[&b, &a]).to_bytes()
+        }
+    }
+
+    #[test]
+    fn public_mint_follows_schedule_and_wallet_limit() {
+        assert_eq!(
+            MintConfig::new(Pubkey::new_unique(), mint_config_args(10, 10)).unwrap_err(),
+            NftStyleOneError::InvalidMintWindow.into()
+        );
+
+        let minter = Pubkey::new_unique();
+        let mut config = MintConfig::new(Pubkey::new_unique(), mint_config_args(10, 20)).unwrap();
+        assert_eq!(
+            config.check_public_mint(&minter, 9, 0, &[]),
+            Err(NftStyleOneError::MintNotStarted.into())
+        );
+        config.check_public_mint(&minter, 10, 0, &[]).unwrap();
+        config
+            .check_public_mint(&minter, 19, u32::MAX, &[])
+            .unwrap();
+        assert_eq!(
+            config.check_public_mint(&minter, 20, 0, &[]),
+            Err(NftStyleOneError::MintEnded.into())
+        );
+
+        config.wallet_limit = Some(2);
+        config.check_public_mint(&minter, 10, 1, &[]).unwrap();
+        assert_eq!(
+            config.check_public_mint(&minter, 10, 2, &[]),
+            Err(NftStyleOneError::WalletLimitReached.into())
+        );
+    }
+
+    #[test]
+    fn allowlist_proofs_verify() {
+        let wallets = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let leaves = wallets.map(|wallet| hashv(&[wallet.as_ref()]).to_bytes());
+        let left = allowlist_node(leaves[0], leaves[1]);
+        let root = allowlist_node(left, leaves[2]);
+
+        assert!(verify_allowlist(
+            &root,
+            &wallets[0],
+            &[leaves[1], leaves[2]]
+        ));
+        assert!(verify_allowlist(
+            &root,
+            &wallets[1],
+            &[leaves[0], leaves[2]]
+        ));
+        assert!(verify_allowlist(&root, &wallets[2], &[left]));
+        assert!(!verify_allowlist(&root, &wallets[2], &[leaves[0]]));
+        assert!(!verify_allowlist(
+            &root,
+            &Pubkey::new_unique(),
+            &[leaves[1], leaves[2]]
+        ));
+
+        let mut config = MintConfig::new(Pubkey::new_unique(), mint_config_args(0, 1)).unwrap();
+        config.allowlist_root = Some(root);
+        config
+            .check_public_mint(&wallets[2], 0, 0, &[left])
+            .unwrap();
+        assert_eq!(
+            config.check_public_mint(&Pubkey::new_unique(), 0, 0, &[left]),
+            Err(NftStyleOneError::NotAllowlisted.into())
+        );
+    }
+
+    #[test]
+    fn hidden_metadata_changes_only_by_reveal() {
+        let mut metadata = metadata(Pubkey::new_unique());
+        for (name, uri) in [("name0", "uri"), ("name", "uri0")] {
+            assert_eq!(
+                metadata.check_hidden_update(name, uri, true),
+                Err(NftStyleOneError::MetadataHidden.into())
+            );
+            metadata.check_hidden_update(name, uri, false).unwrap();
+        }
+        metadata.check_hidden_update("name", "uri", true).unwrap();
+
+        metadata
+            .reveal("name0".to_string(), "uri0".to_string())
+            .unwrap();
+        // From here on `update` rejects renames with `MetadataRevealed` instead
+        metadata.check_hidden_update("name1", "uri1", true).unwrap();
+    }
+
+    #[test]
+    fn reveals_match_the_provenance_hash() {
+        let leaves = [
+            reveal_leaf(0, "name0", "uri0"),
+            reveal_leaf(1, "name1", "uri1"),
+        ];
+        let mut provenance = Provenance {
+            collection: Pubkey::new_unique(),
+            hash: allowlist_node(leaves[0], leaves[1]),
+            revealed: 0,
+        };
+
+        provenance
+            .check_reveal(0, "name0", "uri0", &[leaves[1]])
+            .unwrap();
+        provenance
+            .check_reveal(1, "name1", "uri1", &[leaves[0]])
+            .unwrap();
+        for (collection_num, name, uri) in [
+            (1, "name0", "uri0"),
+            (0, "name0", "uri1"),
+            (0, "name", "0uri0"),
+        ] {
+            assert_eq!(
+                provenance.check_reveal(collection_num, name, uri, &[leaves[1]]),
+                Err(NftStyleOneError::InvalidRevealProof.into())
+            );
+        }
+        provenance.record_reveal().unwrap();
+        assert_eq!(provenance.revealed, 1);
+
+        let mut metadata = metadata(Pubkey::new_unique());
+        metadata
+            .reveal("name0".to_string(), "uri0".to_string())
+            .unwrap();
+        assert_eq!(
+            (metadata.name.as_str(), metadata.uri.as_str()),
+            ("name0", "uri0")
+        );
+        assert!(metadata.revealed);
+        let payload = metadata.tlv_payload().unwrap();
+        assert_eq!(
+            tlv::TlvReader::new(&payload)
+                .get(REVEALED)
+                .unwrap()
+                .unwrap()
+                .value,
+            [1]
+        );
+
+        // Reveals cannot be replayed, and the revealed name and uri are final
+        assert_eq!(
+            metadata.reveal("name0".to_string(), "uri0".to_string()),
+            Err(NftStyleOneError::MetadataRevealed.into())
+        );
+        for (name, uri) in [("name1", "uri0"), ("name0", "uri1")] {
+            assert_eq!(
+                metadata.update(
+                    name.to_string(),
+                    "symbol".to_string(),
+                    uri.to_string(),
+                    vec![]
+                ),
+                Err(NftStyleOneError::MetadataRevealed.into())
+            );
+        }
+        metadata
+            .update(
+                "name0".to_string(),
+                "SYM".to_string(),
+                "uri0".to_string(),
+                vec![attribute("eyes", "laser")],
+            )
+            .unwrap();
+        assert_eq!(metadata.symbol, "SYM");
+
+        metadata.lock().unwrap();
+        assert_eq!(
+            metadata.reveal("name1".to_string(), "uri1".to_string()),
+            Err(NftStyleOneError::MetadataImmutable.into())
+        );
+    }
+}
@@ -1,14 +1,24 @@
 use anchor_lang::prelude::*;
 use anchor_lang::Discriminator;
-use nft_events::{get_collection_discriminator, get_metadata_discriminator};
-use serde::{self, Serialize};
+use nft_events::tlv::{self, encode_tlv, TlvWriter};
+use nft_events::{
+    emit_nft_delegated, emit_nft_revoked, emit_nft_transferred, event_emitter, new_creators,
+    verify_creator, Creator, CreatorError, NftAsset, NftDelegation, NftTransfer, RenderFormat,
+    RenderRequest, CREATOR_LEN, FLAG_TLV_PAYLOAD, MAX_RENDERED_LEN,
+};
 
-use bs58_pubkey::serde_pubkey;
+use additional_accounts_request::{IAccountMeta, PreflightPayload};
+use bs58_pubkey::json::JsonWriter;
 
 declare_id!("9CB3S1yQhyxf5KFeRa6RYj2Np9qwiUpofZYoDQNKphMo");
 
+/// TLV key of the edition metadata's description
+pub const EDITION_DESCRIPTION: &str = "description";
+
 #[program]
 pub mod nft_style_two {
+    use anchor_lang::solana_program::program::{set_return_data, MAX_RETURN_DATA};
+
     use super::*;
 
     pub fn initialize(
@@ -33,39 +43,26 @@ pub mod nft_style_two {
             num_versions: edition_num_versions,
         });
 
-        // Issue a collection
-        emit_cpi!({
-            CudCreate {
-                authority: ctx.accounts.owner.key(),
-                asset_id: ctx.accounts.collection.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.edition_metadata.key(),
-                ],
-                data: get_collection_discriminator()?,
-            }
-        });
-        emit_cpi!({
-            CudCreate {
-                authority: ctx.accounts.owner.key(),
-                asset_id: ctx.accounts.edition_metadata.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.edition_metadata.key(),
-                ],
-                data: vec![],
-            }
-        });
+        // Issue a collection, and the edition metadata that groups the editions
+        ctx.accounts
+            .collection
+            .emit_create(ctx.accounts.collection.key(), &event_emitter!())?;
+        ctx.accounts
+            .edition_metadata
+            .emit_create(ctx.accounts.edition_metadata.key(), &event_emitter!())?;
 
         Ok(())
     }
 
+    /// Mints the master edition, which can print up to `max_supply` editions numbered from 1
     pub fn mint_master_edition(
         ctx: Context<MintMasterEdition>,
         collection_num: u32,
+        max_supply: u32,
         name: String,
         symbol: String,
         uri: String,
+        creators: Vec<Creator>,
     ) -> Result<()> {
         assert!(
             collection_num <= ctx.accounts.collection.num_items,
@@ -74,49 +71,43 @@ pub mod nft_style_two {
         );
 
         ctx.accounts.metadata.set_inner(Metadata {
+            collection: ctx.accounts.collection.key(),
+            collection_num,
+            edition_metadata: ctx.accounts.edition_metadata.key(),
+            edition: ctx.accounts.master_edition.key(),
             name,
             symbol,
             uri,
             owner: *ctx.accounts.owner.key,
+            delegate: *ctx.accounts.owner.key,
+            creators: new_creators(creators, ctx.accounts.owner.key)
+                .map_err(NftStyleTwoError::from)?,
         });
+        require!(
+            max_supply <= ctx.accounts.edition_metadata.num_versions,
+            NftStyleTwoError::InvalidMaxSupply
+        );
         ctx.accounts.master_edition.set_inner(MasterEdition {
             authority: *ctx.accounts.owner.key,
+            supply: 0,
+            max_supply,
+            next_edition: 1,
         });
 
         // Issue a metadata
-        emit_cpi!({
-            CudCreate {
-                authority: ctx.accounts.owner.key(),
-                asset_id: ctx.accounts.master_edition.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.edition_metadata.key(),
-                    ctx.accounts.master_edition.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.metadata.key(),
-                ],
-                data: vec![],
-            }
-        });
-        emit_cpi!({
-            CudCreate {
-                authority: ctx.accounts.owner.key(),
-                asset_id: ctx.accounts.metadata.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.edition_metadata.key(),
-                    ctx.accounts.master_edition.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.metadata.key(),
-                ],
-                data: get_metadata_discriminator()?,
-            }
-        });
+        ctx.accounts
+            .metadata
+            .edition_asset(ctx.accounts.metadata.key())
+            .emit_create(ctx.accounts.master_edition.key(), &event_emitter!())?;
+        ctx.accounts
+            .metadata
+            .emit_create(ctx.accounts.metadata.key(), &event_emitter!())?;
 
         Ok(())
     }
 
+    /// Prints edition `edition_num` of the master edition, signed by the master edition's
+    /// owner or authority
     pub fn mint_edition(
         ctx: Context<MintEdition>,
         collection_num: u32,
@@ -124,23 +115,84 @@ pub mod nft_style_two {
         name: String,
         symbol: String,
         uri: String,
+        creators: Vec<Creator>,
     ) -> Result<()> {
         assert!(
             collection_num <= ctx.accounts.collection.num_items,
             "Collection item number cannot exceed set maximum value: {}",
             ctx.accounts.collection.num_items
         );
+        ctx.accounts.master_edition.check_print(
+            ctx.accounts.owner.key,
+            &ctx.accounts.master_metadata.owner,
+            edition_num,
+        )?;
+        ctx.accounts.master_edition.record_print(edition_num)?;
+
+        ctx.accounts.metadata.set_inner(Metadata {
+            collection: ctx.accounts.collection.key(),
+            collection_num,
+            edition_metadata: ctx.accounts.edition_metadata.key(),
+            edition: ctx.accounts.edition.key(),
+            name,
+            symbol,
+            uri,
+            owner: *ctx.accounts.owner.key,
+            delegate: *ctx.accounts.owner.key,
+            creators: new_creators(creators, ctx.accounts.owner.key)
+                .map_err(NftStyleTwoError::from)?,
+        });
+        ctx.accounts.edition.set_inner(Edition {
+            authority: *ctx.accounts.owner.key,
+            version: edition_num,
+        });
+
+        // Issue a metadata
+        ctx.accounts
+            .metadata
+            .edition_asset(ctx.accounts.metadata.key())
+            .emit_create(ctx.accounts.edition.key(), &event_emitter!())?;
+        ctx.accounts
+            .metadata
+            .emit_create(ctx.accounts.metadata.key(), &event_emitter!())?;
+
+        Ok(())
+    }
+
+    /// Prints the next edition of the master edition, numbered by its `next_edition` counter
+    pub fn print_edition(
+        ctx: Context<PrintEdition>,
+        collection_num: u32,
+        name: String,
+        symbol: String,
+        uri: String,
+        creators: Vec<Creator>,
+    ) -> Result<()> {
         assert!(
-            edition_num <= ctx.accounts.edition_metadata.num_versions,
-            "Edition number cannot exceed set maximum value: {}",
-            ctx.accounts.edition_metadata.num_versions
+            collection_num <= ctx.accounts.collection.num_items,
+            "Collection item number cannot exceed set maximum value: {}",
+            ctx.accounts.collection.num_items
         );
+        let edition_num = ctx.accounts.master_edition.next_edition;
+        ctx.accounts.master_edition.check_print(
+            ctx.accounts.owner.key,
+            &ctx.accounts.master_metadata.owner,
+            edition_num,
+        )?;
+        ctx.accounts.master_edition.record_print(edition_num)?;
 
         ctx.accounts.metadata.set_inner(Metadata {
+            collection: ctx.accounts.collection.key(),
+            collection_num,
+            edition_metadata: ctx.accounts.edition_metadata.key(),
+            edition: ctx.accounts.edition.key(),
             name,
             symbol,
             uri,
             owner: *ctx.accounts.owner.key,
+            delegate: *ctx.accounts.owner.key,
+            creators: new_creators(creators, ctx.accounts.owner.key)
+                .map_err(NftStyleTwoError::from)?,
         });
         ctx.accounts.edition.set_inner(Edition {
             authority: *ctx.accounts.owner.key,
@@ -148,158 +200,840 @@ pub mod nft_style_two {
         });
 
         // Issue a metadata
-        emit_cpi!({
-            CudCreate {
-                authority: ctx.accounts.owner.key(),
-                asset_id: ctx.accounts.edition.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.edition_metadata.key(),
-                    ctx.accounts.edition.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.metadata.key(),
-                ],
-                data: vec![],
-            }
-        });
-        emit_cpi!({
-            CudCreate {
-                authority: ctx.accounts.owner.key(),
-                asset_id: ctx.accounts.metadata.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.edition_metadata.key(),
-                    ctx.accounts.edition.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.metadata.key(),
-                ],
-                data: get_metadata_discriminator()?,
-            }
+        ctx.accounts
+            .metadata
+            .edition_asset(ctx.accounts.metadata.key())
+            .emit_create(ctx.accounts.edition.key(), &event_emitter!())?;
+        ctx.accounts
+            .metadata
+            .emit_create(ctx.accounts.metadata.key(), &event_emitter!())?;
+
+        Ok(())
+    }
+
+    /// Upgrades metadata minted by the first release, which only stored the owner, name, symbol
+    /// and uri. The asset starts out undelegated and with no creators.
+    ///
+    /// Those accounts did not record their collection or edition, so they are passed in: the
+    /// asset must be the collection's metadata PDA for `collection_num`, and the edition must
+    /// be the master edition or a print of `edition_metadata`, still in its first released
+    /// layout. Migrate a master edition's metadata before `migrate_master_edition`.
+    ///
+    /// As the pairing of metadata and edition was never stored either, each edition is claimed
+    /// once through an `EditionClaim`. The owner can only claim an edition it minted. The
+    /// edition metadata's authority can pair prints with any metadata, but master editions,
+    /// whose holder may print, only with metadata still owned by their minter. The signer pays
+    /// the claim and the extra rent.
+    pub fn migrate_metadata(ctx: Context<MigrateMetadata>, collection_num: u32) -> Result<()> {
+        let info = ctx.accounts.asset.to_account_info();
+        let edition_metadata = ctx.accounts.edition_metadata.key();
+        let edition = legacy_edition(
+            &ctx.accounts.edition.to_account_info(),
+            &edition_metadata,
+            &ctx.accounts.edition_metadata,
+            ctx.program_id,
+        )?;
+        let metadata = Metadata::from_legacy(
+            &info.try_borrow_data()?,
+            ctx.accounts.collection.key(),
+            collection_num,
+            edition_metadata,
+            ctx.accounts.edition.key(),
+        )?;
+        check_legacy_claim(
+            &edition,
+            &metadata,
+            &ctx.accounts.authority.key(),
+            &ctx.accounts.edition_metadata.authority,
+        )?;
+        ctx.accounts.claim.set_inner(EditionClaim {
+            metadata: info.key(),
         });
 
+        let space = Metadata::space(
+            &metadata.name,
+            &metadata.symbol,
+            &metadata.uri,
+            &metadata.creators,
+        );
+        let rent = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(info.lamports());
+        if rent > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        info.realloc(space, false)?;
+        metadata.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        metadata.emit_update(info.key(), &event_emitter!())?;
+        metadata
+            .edition_asset(info.key())
+            .emit_update(ctx.accounts.edition.key(), &event_emitter!())?;
         Ok(())
     }
 
-    pub fn transfer(
-        ctx: Context<TransferMe>,
-        _collection_num: u32,
-        _edition_num: u32,
+    /// Upgrades a master edition minted by the first release, which only stored its authority.
+    /// Its metadata must be migrated first, as `migrate_metadata` only pairs legacy editions.
+    ///
+    /// That layout did not limit or count prints, so its authority supplies `max_supply`, the
+    /// `supply` of prints not burned, and `next_edition`, the number `print_edition` continues
+    /// from. The account is resized in place and the authority pays any extra rent.
+    pub fn migrate_master_edition(
+        ctx: Context<MigrateMasterEdition>,
+        max_supply: u32,
+        supply: u32,
+        next_edition: u32,
     ) -> Result<()> {
-        ctx.accounts.metadata.owner = *ctx.accounts.dest.key;
-
-        emit_cpi!({
-            CudUpdate {
-                asset_id: ctx.accounts.metadata.key(),
-                authority: ctx.accounts.dest.key(),
-                pubkeys: vec![
-                    ctx.accounts.collection.key(),
-                    ctx.accounts.dest.key(),
-                    ctx.accounts.edition.key(),
-                    ctx.accounts.owner.key(),
-                    ctx.accounts.metadata.key(),
-                ],
-                data: get_metadata_discriminator()?,
-            }
+        let info = ctx.accounts.master_edition.to_account_info();
+        let edition_metadata = &ctx.accounts.edition_metadata;
+        let mut master_edition =
+            MasterEdition::from_legacy(&info.try_borrow_data()?, edition_metadata)?;
+        require_keys_eq!(
+            master_edition.authority,
+            ctx.accounts.authority.key(),
+            NftStyleTwoError::NotMasterEditionHolder
+        );
+        require!(
+            max_supply <= edition_metadata.num_versions,
+            NftStyleTwoError::InvalidMaxSupply
+        );
+        master_edition.set_counts(max_supply, supply, next_edition)?;
+
+        let rent = Rent::get()?
+            .minimum_balance(MasterEdition::LEN)
+            .saturating_sub(info.lamports());
+        if rent > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        info.realloc(MasterEdition::LEN, false)?;
+        master_edition.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+
+    pub fn preflight_transfer(ctx: Context<ITransfer>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            false,
+        )?);
+        Ok(())
+    }
+
+    /// Moves the asset to `destination`, signed by its owner or delegate. Clears any delegation.
+    ///
+    /// Takes the standard `ITransfer` accounts, followed by the collection, edition
+    /// metadata, edition and event accounts resolved by `preflight_transfer`.
+    pub fn transfer(ctx: Context<TransferMe>) -> Result<()> {
+        ctx.accounts
+            .asset
+            .check_owner_or_delegate(ctx.accounts.authority.key)?;
+        let from = ctx.accounts.asset.owner;
+        let destination = ctx.accounts.destination.key();
+        ctx.accounts.asset.transfer_to(destination);
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        ctx.accounts
+            .asset
+            .edition_asset(ctx.accounts.asset.key())
+            .emit_update(ctx.accounts.edition.key(), &event_emitter!())?;
+
+        emit_nft_transferred!(NftTransfer {
+            asset: ctx.accounts.asset.key(),
+            from,
+            to: destination,
+            authority: ctx.accounts.authority.key(),
         });
 
         Ok(())
     }
 
-    pub fn burn(ctx: Context<BurnMe>, _collection_num: u32, _edition_num: u32) -> Result<()> {
-        emit_cpi!({
-            CudDelete {
-                asset_id: ctx.accounts.metadata.key(),
-            }
+    pub fn preflight_delegate(ctx: Context<IDelegate>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            false,
+        )?);
+        Ok(())
+    }
+
+    /// Lets `delegate` transfer the asset until the owner revokes it or the asset is transferred
+    pub fn delegate(ctx: Context<DelegateMe>) -> Result<()> {
+        ctx.accounts.asset.set_delegate(*ctx.accounts.delegate.key);
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        ctx.accounts
+            .asset
+            .edition_asset(ctx.accounts.asset.key())
+            .emit_update(ctx.accounts.edition.key(), &event_emitter!())?;
+        emit_nft_delegated!(NftDelegation {
+            asset: ctx.accounts.asset.key(),
+            owner: ctx.accounts.owner.key(),
+            delegate: ctx.accounts.delegate.key(),
+            authority: ctx.accounts.owner.key(),
         });
 
         Ok(())
     }
 
-    pub fn get_asset_data(ctx: Context<GetAssetDataAccounts>, _data: Vec<u8>) -> Result<()> {
-        let data = ctx.accounts.asset_id.try_borrow_mut_data()?;
+    pub fn preflight_revoke(ctx: Context<IRevoke>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            false,
+        )?);
+        Ok(())
+    }
 
-        let account_disc = &data[0..8];
-        let json_data = if *account_disc == Metadata::DISCRIMINATOR {
-            let metadata_info = Metadata::try_from_slice(&data[8..data.len()])?;
+    pub fn revoke(ctx: Context<RevokeMe>) -> Result<()> {
+        let delegate = ctx.accounts.asset.delegate;
+        ctx.accounts.asset.revoke();
 
-            let data = serde_json::to_string(&metadata_info).unwrap();
-            msg!("Found metadata: {}", &data);
-            data
-        } else if *account_disc == Collection::DISCRIMINATOR {
-            let collection_info = Collection::try_from_slice(&data[8..data.len()])?;
-            let data = serde_json::to_string(&collection_info).unwrap();
-            msg!("Found collection: {}", &data);
-            data
-        } else if *account_disc == EditionMetadata::DISCRIMINATOR {
-            let edition_metadata = EditionMetadata::try_from_slice(&data[8..data.len()])?;
-            let data = serde_json::to_string(&edition_metadata).unwrap();
-            msg!("Found collection: {}", &data);
-            data
-        } else if *account_disc == Edition::DISCRIMINATOR {
-            let edition = Edition::try_from_slice(&data[8..data.len()])?;
-            let data = serde_json::to_string(&edition).unwrap();
-            msg!("Found edition: {}", &data);
-            data
-        } else if *account_disc == MasterEdition::DISCRIMINATOR {
-            let master_edition = MasterEdition::try_from_slice(&data[8..data.len()])?;
-            let data = serde_json::to_string(&master_edition).unwrap();
-            msg!("Found master edition: {}", &data);
-            data
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+        ctx.accounts
+            .asset
+            .edition_asset(ctx.accounts.asset.key())
+            .emit_update(ctx.accounts.edition.key(), &event_emitter!())?;
+        emit_nft_revoked!(NftDelegation {
+            asset: ctx.accounts.asset.key(),
+            owner: ctx.accounts.owner.key(),
+            delegate,
+            authority: ctx.accounts.owner.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn preflight_burn(ctx: Context<IBurn>) -> Result<()> {
+        set_return_data(&preflight_asset_accounts(
+            &ctx.accounts.asset,
+            ctx.program_id,
+            true,
+        )?);
+        Ok(())
+    }
+
+    /// Destroys the asset and its edition, refunding both rents to `rent_recipient`.
+    /// Closing the edition frees its number to be minted again.
+    ///
+    /// A master edition can only be burned once none of its prints are left.
+    pub fn burn(ctx: Context<BurnMe>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            ctx.accounts.asset.owner,
+            NftStyleTwoError::NotOwner
+        );
+        if ctx.accounts.asset.edition == ctx.accounts.master_edition.key() {
+            require!(
+                ctx.accounts.master_edition.supply == 0,
+                NftStyleTwoError::MasterEditionHasPrints
+            );
         } else {
-            "".to_string()
-        };
-        anchor_lang::solana_program::program::set_return_data(json_data.as_bytes());
+            ctx.accounts.master_edition.record_burn()?;
+        }
+        close_account(
+            &ctx.accounts.edition.to_account_info(),
+            &ctx.accounts.rent_recipient,
+        )?;
+
+        event_emitter!().emit(&CudDelete {
+            asset_id: ctx.accounts.edition.key(),
+        })?;
+        event_emitter!().emit(&CudDelete {
+            asset_id: ctx.accounts.asset.key(),
+        })?;
+
+        Ok(())
+    }
+
+    /// Verifies the signing creator in the asset's creators list
+    pub fn sign_creator(ctx: Context<SignCreator>) -> Result<()> {
+        verify_creator(&mut ctx.accounts.asset.creators, ctx.accounts.creator.key)
+            .map_err(NftStyleTwoError::from)?;
+
+        ctx.accounts
+            .asset
+            .emit_update(ctx.accounts.asset.key(), &event_emitter!())?;
+
+        Ok(())
+    }
+
+    pub fn get_asset_data(
+        ctx: Context<GetAssetDataAccounts>,
+        render_request: Vec<u8>,
+    ) -> Result<()> {
+        let data = ctx.accounts.asset_id.try_borrow_mut_data()?;
+
+        let request = RenderRequest::parse(&render_request);
+        match request.page {
+            Some(page) => {
+                let mut document = vec![0u8; MAX_RENDERED_LEN];
+                let len = render_asset(&data, request.format, &mut document)?;
+                set_return_data(&page.response(&document[..len]));
+            }
+            None => {
+                let mut document = [0u8; MAX_RETURN_DATA];
+                let len = render_asset(&data, request.format, &mut document)?;
+                set_return_data(&document[..len]);
+            }
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Collection, edition metadata and edition of the asset, then the event authority and program
+/// unless events are logged
+fn preflight_asset_accounts(
+    asset: &Metadata,
+    program_id: &Pubkey,
+    edition_writable: bool,
+) -> Result<Vec<u8>> {
+    let account = |pubkey: Pubkey, writable: bool| IAccountMeta {
+        pubkey,
+        signer: false,
+        writable,
+    };
+
+    let mut accounts = vec![
+        account(asset.collection, false),
+        account(asset.edition_metadata, false),
+        account(asset.edition, edition_writable),
+    ];
+    if cfg!(not(feature = "log-events")) {
+        accounts.extend([
+            account(
+                Pubkey::find_program_address(&[b"__event_authority"], program_id).0,
+                false,
+            ),
+            account(*program_id, false),
+        ]);
+    }
+
+    Ok(PreflightPayload { accounts }.try_to_vec()?)
+}
+
+/// Closes an account the program owns, the same way `#[account(close = ..)]` does
+fn close_account<'info>(info: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    let lamports = info.lamports();
+    **destination.lamports.borrow_mut() = destination.lamports().checked_add(lamports).unwrap();
+    **info.lamports.borrow_mut() = 0;
+
+    info.assign(&System::id());
+    info.realloc(0, false)?;
+    Ok(())
+}
+
+/// Renders an asset account into `buf` in the requested format, returning the rendered length
+fn render_asset(data: &[u8], format: RenderFormat, buf: &mut [u8]) -> Result<usize> {
+    let account_disc = &data[0..8];
+    if format == RenderFormat::Tlv {
+        let mut writer = TlvWriter::new(buf);
+        if *account_disc == Metadata::DISCRIMINATOR {
+            Metadata::try_from_slice(&data[8..data.len()])?.write_tlv(&mut writer)?;
+        } else if *account_disc == Collection::DISCRIMINATOR {
+            Collection::try_from_slice(&data[8..data.len()])?.write_tlv(&mut writer)?;
+        }
+        return Ok(writer.len());
+    }
+
+    let mut writer = JsonWriter::new(buf);
+    if *account_disc == Metadata::DISCRIMINATOR {
+        Metadata::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found metadata: {}", writer.as_str());
+    } else if *account_disc == Collection::DISCRIMINATOR {
+        Collection::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found collection: {}", writer.as_str());
+    } else if *account_disc == EditionMetadata::DISCRIMINATOR {
+        EditionMetadata::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found collection: {}", writer.as_str());
+    } else if *account_disc == Edition::DISCRIMINATOR {
+        Edition::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found edition: {}", writer.as_str());
+    } else if *account_disc == MasterEdition::DISCRIMINATOR {
+        MasterEdition::try_from_slice(&data[8..data.len()])?.write_json(&mut writer)?;
+        msg!("Found master edition: {}", writer.as_str());
+    }
+    Ok(writer.len())
+}
+
+#[derive(Debug, NftAsset)]
 #[account]
+#[asset(payload = "tlv_payload", flags = "FLAG_TLV_PAYLOAD")]
 pub struct Metadata {
-    #[serde(with = "serde_pubkey")]
+    #[asset(collection)]
+    collection: Pubkey,
+    collection_num: u32,
+    #[asset(extra)]
+    edition_metadata: Pubkey,
+    /// The edition or master edition minted alongside this metadata
+    #[asset(extra)]
+    edition: Pubkey,
+    #[asset(owner)]
+    owner: Pubkey,
+    #[asset(delegate)]
+    delegate: Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    creators: Vec<Creator>,
+}
+
+impl Metadata {
+    /// Account size for the given strings and creators
+    pub fn space(name: &str, symbol: &str, uri: &str, creators: &[Creator]) -> usize {
+        let strings = 4 + name.len() + 4 + symbol.len() + 4 + uri.len();
+        8 + 32 + 4 + 32 + 32 + 32 + 32 + strings + 4 + creators.len() * CREATOR_LEN
+    }
+
+    /// Reads metadata written by the first release, `{ owner, name, symbol, uri }`, filling in
+    /// the fields it did not store. Those accounts were sized to fit exactly, so one with bytes
+    /// left over is in a later layout.
+    pub fn from_legacy(
+        data: &[u8],
+        collection: Pubkey,
+        collection_num: u32,
+        edition_metadata: Pubkey,
+        edition: Pubkey,
+    ) -> Result<Self> {
+        require!(
+            data.starts_with(&Metadata::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let mut body = &data[8..];
+        let legacy = LegacyMetadata::deserialize(&mut body)
+            .map_err(|_| NftStyleTwoError::NotLegacyMetadata)?;
+        require!(body.is_empty(), NftStyleTwoError::NotLegacyMetadata);
+
+        Ok(Metadata {
+            collection,
+            collection_num,
+            edition_metadata,
+            edition,
+            owner: legacy.owner,
+            delegate: legacy.owner,
+            name: legacy.name,
+            symbol: legacy.symbol,
+            uri: legacy.uri,
+            creators: vec![],
+        })
+    }
+
+    /// An asset is delegated while its delegate differs from its owner
+    pub fn is_delegated(&self) -> bool {
+        self.delegate != self.owner
+    }
+
+    /// The owner can always move the asset, and the delegate can while delegated
+    pub fn is_owner_or_delegate(&self, authority: &Pubkey) -> bool {
+        self.owner == *authority || self.delegate == *authority
+    }
+
+    pub fn check_owner_or_delegate(&self, authority: &Pubkey) -> Result<()> {
+        require!(
+            self.is_owner_or_delegate(authority),
+            NftStyleTwoError::NotOwnerOrDelegate
+        );
+        Ok(())
+    }
+
+    /// Moves the asset to `destination`. Transfers clear any delegation.
+    pub fn transfer_to(&mut self, destination: Pubkey) {
+        self.owner = destination;
+        self.delegate = destination;
+    }
+
+    pub fn set_delegate(&mut self, delegate: Pubkey) {
+        self.delegate = delegate;
+    }
+
+    pub fn revoke(&mut self) {
+        self.delegate = self.owner;
+    }
+
+    /// Name, symbol, uri and creators as a TLV map, emitted as the AssetGroup payload
+    pub fn tlv_payload(&self) -> Result<Vec<u8>> {
+        let len = tlv::entry_len(tlv::NAME, self.name.as_bytes())
+            + tlv::entry_len(tlv::SYMBOL, self.symbol.as_bytes())
+            + tlv::entry_len(tlv::URI, self.uri.as_bytes())
+            + tlv::entry_len(tlv::CREATORS, &[])
+            + self.creators.len() * CREATOR_LEN;
+        let mut payload = vec![0u8; len];
+        let mut writer = TlvWriter::new(&mut payload);
+        writer.write_str(tlv::NAME, &self.name)?;
+        writer.write_str(tlv::SYMBOL, &self.symbol)?;
+        writer.write_str(tlv::URI, &self.uri)?;
+        writer.write_creators(&self.creators)?;
+        Ok(payload)
+    }
+
+    /// AssetGroup of this metadata's edition, where `metadata_id` is this account's key
+    pub fn edition_asset(&self, metadata_id: Pubkey) -> EditionAsset {
+        EditionAsset {
+            edition_metadata: self.edition_metadata,
+            owner: self.owner,
+            delegate: self.delegate,
+            collection: self.collection,
+            metadata: metadata_id,
+        }
+    }
+
+    pub fn write_tlv(&self, writer: &mut TlvWriter) -> Result<()> {
+        writer.write_str(tlv::NAME, &self.name)?;
+        writer.write_str(tlv::SYMBOL, &self.symbol)?;
+        writer.write_str(tlv::URI, &self.uri)?;
+        writer.write_creators(&self.creators)?;
+        writer.write_pubkey(tlv::OWNER, &self.owner)?;
+        writer.write_pubkey(tlv::DELEGATE, &self.delegate)?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("owner", self.owner)?;
+        writer.field_pubkey("delegate", self.delegate)?;
+        writer.field_str("name", &self.name)?;
+        writer.field_str("symbol", &self.symbol)?;
+        writer.field_str("uri", &self.uri)?;
+        writer.key("creators")?;
+        writer.begin_array()?;
+        for creator in &self.creators {
+            writer.begin_object()?;
+            writer.field_pubkey("address", creator.address)?;
+            writer.field_u64("share_bps", creator.share_bps.into())?;
+            writer.field_bool("verified", creator.verified)?;
+            writer.end_object()?;
+        }
+        writer.end_array()?;
+        writer.end_object()?;
+        Ok(())
+    }
+}
+
+/// `Metadata` as first released, read by `Metadata::from_legacy`
+#[derive(AnchorDeserialize)]
+struct LegacyMetadata {
     owner: Pubkey,
     name: String,
     symbol: String,
     uri: String,
 }
 
-#[derive(Debug, Serialize)]
+/// Whether `signer` may pair legacy `metadata` with `edition`, see `migrate_metadata`
+fn check_legacy_claim(
+    edition: &LegacyEdition,
+    metadata: &Metadata,
+    signer: &Pubkey,
+    edition_metadata_authority: &Pubkey,
+) -> Result<()> {
+    let minted_by_owner = edition.authority == metadata.owner;
+    if signer == edition_metadata_authority {
+        require!(
+            minted_by_owner || !edition.is_master,
+            NftStyleTwoError::EditionMismatch
+        );
+    } else {
+        require_keys_eq!(metadata.owner, *signer, NftStyleTwoError::NotOwner);
+        require!(minted_by_owner, NftStyleTwoError::EditionMismatch);
+    }
+    Ok(())
+}
+
+/// An edition or master edition still in the first released layout, as read by `legacy_edition`
+#[derive(Debug, PartialEq)]
+struct LegacyEdition {
+    /// Who minted it, along with the metadata of that time
+    authority: Pubkey,
+    is_master: bool,
+}
+
+/// Checks that `edition` is the master edition or a print of `edition_metadata`, in the layout
+/// the first release minted it with. Prints minted since are sized to fit and master editions
+/// get counters, so editions that already have current metadata can't be claimed again.
+fn legacy_edition(
+    edition: &AccountInfo,
+    edition_metadata_key: &Pubkey,
+    edition_metadata: &EditionMetadata,
+    program_id: &Pubkey,
+) -> Result<LegacyEdition> {
+    let data = edition.try_borrow_data()?;
+    let is_master = data.starts_with(&MasterEdition::DISCRIMINATOR);
+    let (authority, expected) = if is_master {
+        let master_edition = MasterEdition::from_legacy(&data, edition_metadata)
+            .map_err(|_| NftStyleTwoError::EditionMismatch)?;
+        let expected = Pubkey::find_program_address(
+            &[edition_metadata_key.as_ref(), b"master_edition".as_ref()],
+            program_id,
+        )
+        .0;
+        (master_edition.authority, expected)
+    } else {
+        let print = Edition::try_deserialize(&mut &data[..])?;
+        require!(
+            data.len() == edition_metadata.legacy_edition_len(),
+            NftStyleTwoError::EditionMismatch
+        );
+        let expected = Pubkey::find_program_address(
+            &[
+                edition_metadata_key.as_ref(),
+                b"version".as_ref(),
+                &print.version.to_le_bytes(),
+            ],
+            program_id,
+        )
+        .0;
+        (print.authority, expected)
+    };
+    require_keys_eq!(expected, edition.key(), NftStyleTwoError::EditionMismatch);
+
+    Ok(LegacyEdition {
+        authority,
+        is_master,
+    })
+}
+
+#[derive(Debug, NftAsset)]
 #[account]
+#[asset(payload = "tlv_payload", flags = "FLAG_TLV_PAYLOAD")]
 pub struct Collection {
-    #[serde(with = "serde_pubkey")]
+    #[asset(owner)]
     pub authority: Pubkey,
     pub name: String,
     pub symbol: String,
     pub num_items: u32,
 }
 
-#[derive(Debug, Serialize)]
+impl Collection {
+    /// Name and symbol as a TLV map, emitted as the AssetGroup payload
+    pub fn tlv_payload(&self) -> Result<Vec<u8>> {
+        Ok(encode_tlv(&[
+            (tlv::NAME, self.name.as_bytes()),
+            (tlv::SYMBOL, self.symbol.as_bytes()),
+        ])?)
+    }
+
+    pub fn write_tlv(&self, writer: &mut TlvWriter) -> Result<()> {
+        writer.write_str(tlv::NAME, &self.name)?;
+        writer.write_str(tlv::SYMBOL, &self.symbol)?;
+        writer.write_pubkey(tlv::AUTHORITY, &self.authority)?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("authority", self.authority)?;
+        writer.field_str("name", &self.name)?;
+        writer.field_str("symbol", &self.symbol)?;
+        writer.field_u64("num_items", self.num_items.into())?;
+        writer.end_object()?;
+        Ok(())
+    }
+}
+
+/// Groups the master edition and its prints, so it is emitted as a collection of its own
+#[derive(Debug, NftAsset)]
 #[account]
+#[asset(payload = "tlv_payload", flags = "FLAG_TLV_PAYLOAD")]
 pub struct EditionMetadata {
-    #[serde(with = "serde_pubkey")]
+    #[asset(owner)]
     pub authority: Pubkey,
     pub title: String,
     pub description: String,
     pub num_versions: u32,
 }
 
-#[derive(Debug, Serialize)]
+impl EditionMetadata {
+    /// Size the first release gave editions and master editions of this edition metadata
+    pub fn legacy_edition_len(&self) -> usize {
+        8 + 32 + 4 + self.title.len() + 4 + self.description.len()
+    }
+
+    /// Title and description as a TLV map, emitted as the AssetGroup payload
+    pub fn tlv_payload(&self) -> Result<Vec<u8>> {
+        Ok(encode_tlv(&[
+            (tlv::NAME, self.title.as_bytes()),
+            (EDITION_DESCRIPTION, self.description.as_bytes()),
+        ])?)
+    }
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("authority", self.authority)?;
+        writer.field_str("title", &self.title)?;
+        writer.field_str("description", &self.description)?;
+        writer.field_u64("num_versions", self.num_versions.into())?;
+        writer.end_object()?;
+        Ok(())
+    }
+}
+
+/// AssetGroup of an edition or master edition. Editions belong to their edition metadata
+/// rather than the collection, and are listed with the delegate or owner of the metadata
+/// they were minted with, followed by the collection and that metadata.
+#[derive(NftAsset)]
+pub struct EditionAsset {
+    #[asset(collection)]
+    pub edition_metadata: Pubkey,
+    #[asset(owner)]
+    pub owner: Pubkey,
+    #[asset(delegate)]
+    pub delegate: Pubkey,
+    #[asset(extra)]
+    pub collection: Pubkey,
+    #[asset(extra)]
+    pub metadata: Pubkey,
+}
+
+/// Marks an edition as paired with the metadata `migrate_metadata` upgraded for it, at
+/// `[edition, "claim"]`, so that no other metadata can claim it
+#[account]
+pub struct EditionClaim {
+    pub metadata: Pubkey,
+}
+
+impl EditionClaim {
+    pub const LEN: usize = 8 + 32;
+}
+
+#[derive(Debug)]
 #[account]
 pub struct Edition {
-    #[serde(with = "serde_pubkey")]
     pub authority: Pubkey,
     pub version: u32,
 }
 
-#[derive(Debug, Serialize)]
+impl Edition {
+    pub const LEN: usize = 8 + 32 + 4;
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("authority", self.authority)?;
+        writer.field_u64("version", self.version.into())?;
+        writer.end_object()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
 #[account]
 pub struct MasterEdition {
-    #[serde(with = "serde_pubkey")]
     pub authority: Pubkey,
+    /// Editions printed and not burned
+    pub supply: u32,
+    /// Editions are numbered `1..=max_supply`
+    pub max_supply: u32,
+    /// The number `print_edition` prints next
+    pub next_edition: u32,
+}
+
+impl MasterEdition {
+    pub const LEN: usize = 8 + 32 + 4 + 4 + 4;
+
+    /// Reads a master edition minted by the first release: its authority, in an account sized
+    /// like the edition metadata's title and description and otherwise left zeroed. Current
+    /// master editions never have a zero `next_edition`, so they can't pass for one.
+    pub fn from_legacy(data: &[u8], edition_metadata: &EditionMetadata) -> Result<Self> {
+        require!(
+            data.starts_with(&MasterEdition::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(
+            data.len() == edition_metadata.legacy_edition_len()
+                && data[40..].iter().all(|&byte| byte == 0),
+            NftStyleTwoError::NotLegacyMasterEdition
+        );
+
+        Ok(MasterEdition {
+            authority: Pubkey::new_from_array(data[8..40].try_into().unwrap()),
+            supply: 0,
+            max_supply: 0,
+            next_edition: 1,
+        })
+    }
+
+    /// Sets the counters of a migrated master edition. Prints not burned were numbered below
+    /// `next_edition`, which can be at most one past `max_supply`.
+    pub fn set_counts(&mut self, max_supply: u32, supply: u32, next_edition: u32) -> Result<()> {
+        require!(
+            supply < next_edition && next_edition <= max_supply.saturating_add(1),
+            NftStyleTwoError::InvalidEditionCounts
+        );
+        self.max_supply = max_supply;
+        self.supply = supply;
+        self.next_edition = next_edition;
+        Ok(())
+    }
+
+    /// Only the master edition's owner or authority can print, and only numbers up to `max_supply`
+    pub fn check_print(&self, printer: &Pubkey, holder: &Pubkey, edition_num: u32) -> Result<()> {
+        require!(
+            *printer == self.authority || *printer == *holder,
+            NftStyleTwoError::NotMasterEditionHolder
+        );
+        require!(
+            self.supply < self.max_supply,
+            NftStyleTwoError::MaxSupplyReached
+        );
+        require!(
+            edition_num >= 1 && edition_num <= self.max_supply,
+            NftStyleTwoError::EditionNumOutOfRange
+        );
+        Ok(())
+    }
+
+    /// Counts a print. `next_edition` moves past explicitly numbered prints, but does not
+    /// skip numbers below it that are already taken.
+    pub fn record_print(&mut self, edition_num: u32) -> Result<()> {
+        self.supply = self
+            .supply
+            .checked_add(1)
+            .ok_or(NftStyleTwoError::MaxSupplyReached)?;
+        self.next_edition = self.next_edition.max(edition_num.saturating_add(1));
+        Ok(())
+    }
+
+    pub fn record_burn(&mut self) -> Result<()> {
+        self.supply = self
+            .supply
+            .checked_sub(1)
+            .ok_or(NftStyleTwoError::SupplyUnderflow)?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, writer: &mut JsonWriter) -> Result<()> {
+        writer.begin_object()?;
+        writer.field_pubkey("authority", self.authority)?;
+        writer.field_u64("supply", self.supply.into())?;
+        writer.field_u64("max_supply", self.max_supply.into())?;
+        writer.field_u64("next_edition", self.next_edition.into())?;
+        writer.end_object()?;
+        Ok(())
+    }
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
 #[instruction(
     collection_name: String,
@@ -319,62 +1053,162 @@ pub struct Initialize<'info> {
     pub system_program: Program<'info, System>,
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
-#[instruction(collection_num: u32, name: String, symbol: String, uri: String)]
+#[instruction(collection_num: u32, max_supply: u32, name: String, symbol: String, uri: String, creators: Vec<Creator>)]
 pub struct MintMasterEdition<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
     pub collection: Account<'info, Collection>,
     pub edition_metadata: Account<'info, EditionMetadata>,
-    #[account(init, payer=owner, space = 8 + 32 + 4 + edition_metadata.title.len() + 4 + edition_metadata.description.len(), seeds = [edition_metadata.key().as_ref(), b"master_edition".as_ref()], bump)]
+    #[account(init, payer=owner, space = MasterEdition::LEN, seeds = [edition_metadata.key().as_ref(), b"master_edition".as_ref()], bump)]
     pub master_edition: Account<'info, MasterEdition>,
-    #[account(init, payer=owner, space = 8 + 32 + 4 + name.len() + 4 + symbol.len() + 4 + uri.len(), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
+    #[account(init, payer=owner, space = Metadata::space(&name, &symbol, &uri, &creators), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
     pub metadata: Account<'info, Metadata>,
     pub system_program: Program<'info, System>,
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
-#[instruction(collection_num: u32, edition_num: u32, name: String, symbol: String, uri: String)]
+#[instruction(collection_num: u32, edition_num: u32, name: String, symbol: String, uri: String, creators: Vec<Creator>)]
 pub struct MintEdition<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
     pub collection: Account<'info, Collection>,
     pub edition_metadata: Account<'info, EditionMetadata>,
-    #[account(init, payer=owner, space = 8 + 32 + 4 + edition_metadata.title.len() + 4 + edition_metadata.description.len(), seeds = [edition_metadata.key().as_ref(), b"version".as_ref(), &edition_num.to_le_bytes()], bump)]
+    #[account(mut, seeds = [edition_metadata.key().as_ref(), b"master_edition".as_ref()], bump)]
+    pub master_edition: Account<'info, MasterEdition>,
+    /// The master edition's metadata, whose owner may print
+    #[account(constraint = master_metadata.edition == master_edition.key() @ NftStyleTwoError::NotMasterEditionHolder)]
+    pub master_metadata: Account<'info, Metadata>,
+    #[account(init, payer=owner, space = Edition::LEN, seeds = [edition_metadata.key().as_ref(), b"version".as_ref(), &edition_num.to_le_bytes()], bump)]
     pub edition: Account<'info, Edition>,
-    #[account(init, payer=owner, space = 8 + 32 + 4 + name.len() + 4 + symbol.len() + 4 + uri.len(), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
+    #[account(init, payer=owner, space = Metadata::space(&name, &symbol, &uri, &creators), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
     pub metadata: Account<'info, Metadata>,
     pub system_program: Program<'info, System>,
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
-#[instruction(collection_num: u32, edition_num: u32)]
-pub struct TransferMe<'info> {
+#[instruction(collection_num: u32, name: String, symbol: String, uri: String, creators: Vec<Creator>)]
+pub struct PrintEdition<'info> {
+    #[account(mut)]
     pub owner: Signer<'info>,
-    /// CHECK: recipient
-    pub dest: AccountInfo<'info>,
     pub collection: Account<'info, Collection>,
     pub edition_metadata: Account<'info, EditionMetadata>,
-    #[account(mut, seeds = [edition_metadata.key().as_ref(), b"version".as_ref(), &edition_num.to_le_bytes()], bump)]
+    #[account(mut, seeds = [edition_metadata.key().as_ref(), b"master_edition".as_ref()], bump)]
+    pub master_edition: Account<'info, MasterEdition>,
+    /// The master edition's metadata, whose owner may print
+    #[account(constraint = master_metadata.edition == master_edition.key() @ NftStyleTwoError::NotMasterEditionHolder)]
+    pub master_metadata: Account<'info, Metadata>,
+    #[account(init, payer=owner, space = Edition::LEN, seeds = [edition_metadata.key().as_ref(), b"version".as_ref(), &master_edition.next_edition.to_le_bytes()], bump)]
     pub edition: Account<'info, Edition>,
-    #[account(mut, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
+    #[account(init, payer=owner, space = Metadata::space(&name, &symbol, &uri, &creators), seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
     pub metadata: Account<'info, Metadata>,
+    pub system_program: Program<'info, System>,
 }
 
-#[event_cpi]
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
 #[derive(Accounts)]
-#[instruction(collection_num: u32, edition_num: u32)]
-pub struct BurnMe<'info> {
+pub struct TransferMe<'info> {
+    /// CHECK: must be the asset's current owner
+    pub owner: AccountInfo<'info>,
+    /// CHECK: recipient
+    pub destination: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = owner @ NftStyleTwoError::OwnerMismatch, has_one = collection, has_one = edition_metadata, has_one = edition, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub collection: Account<'info, Collection>,
+    pub edition_metadata: Account<'info, EditionMetadata>,
+    /// CHECK: the asset's edition or master edition, checked by `has_one`
+    pub edition: UncheckedAccount<'info>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+#[instruction(collection_num: u32)]
+pub struct MigrateMetadata<'info> {
+    /// The asset's owner, or the edition metadata's authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: a `Metadata` in the first released layout, checked by `Metadata::from_legacy`
+    #[account(mut, owner = crate::ID, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
+    pub asset: UncheckedAccount<'info>,
+    pub collection: Account<'info, Collection>,
+    pub edition_metadata: Account<'info, EditionMetadata>,
+    /// CHECK: the asset's edition or master edition, checked by `legacy_edition`
+    #[account(owner = crate::ID)]
+    pub edition: UncheckedAccount<'info>,
+    /// Fails to initialize if another metadata already claimed the edition
+    #[account(init, payer = authority, space = EditionClaim::LEN, seeds = [edition.key().as_ref(), b"claim".as_ref()], bump)]
+    pub claim: Account<'info, EditionClaim>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMasterEdition<'info> {
+    /// The master edition's authority
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub edition_metadata: Account<'info, EditionMetadata>,
+    /// CHECK: a `MasterEdition` in the first released layout, checked by `MasterEdition::from_legacy`
+    #[account(mut, owner = crate::ID, seeds = [edition_metadata.key().as_ref(), b"master_edition".as_ref()], bump)]
+    pub master_edition: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct DelegateMe<'info> {
     pub owner: Signer<'info>,
+    /// CHECK: any account can be a delegate
+    pub delegate: AccountInfo<'info>,
+    #[account(mut, has_one = owner, has_one = collection, has_one = edition_metadata, has_one = edition, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
     pub collection: Account<'info, Collection>,
     pub edition_metadata: Account<'info, EditionMetadata>,
-    #[account(mut, seeds = [edition_metadata.key().as_ref(), b"version".as_ref(), &edition_num.to_le_bytes()], bump)]
-    pub edition: Account<'info, Edition>,
-    #[account(mut, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &collection_num.to_le_bytes()], bump)]
-    pub metadata: Account<'info, Metadata>,
+    /// CHECK: the asset's edition or master edition, checked by `has_one`
+    pub edition: UncheckedAccount<'info>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct RevokeMe<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, has_one = owner, has_one = collection, has_one = edition_metadata, has_one = edition, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub collection: Account<'info, Collection>,
+    pub edition_metadata: Account<'info, EditionMetadata>,
+    /// CHECK: the asset's edition or master edition, checked by `has_one`
+    pub edition: UncheckedAccount<'info>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct BurnMe<'info> {
+    pub authority: Signer<'info>,
+    /// CHECK: receives the rent of the asset and its edition
+    #[account(mut)]
+    pub rent_recipient: AccountInfo<'info>,
+    #[account(mut, close = rent_recipient, has_one = collection, has_one = edition_metadata, has_one = edition, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
+    pub collection: Account<'info, Collection>,
+    pub edition_metadata: Account<'info, EditionMetadata>,
+    /// CHECK: the asset's edition or master edition, checked by `has_one` and closed in `burn`
+    #[account(mut, owner = crate::ID)]
+    pub edition: UncheckedAccount<'info>,
+    /// Counts the burned print, or is the edition itself when burning the master edition
+    #[account(mut, seeds = [edition_metadata.key().as_ref(), b"master_edition".as_ref()], bump)]
+    pub master_edition: Account<'info, MasterEdition>,
+}
+
+#[cfg_attr(not(feature = "log-events"), event_cpi)]
+#[derive(Accounts)]
+pub struct SignCreator<'info> {
+    pub creator: Signer<'info>,
+    pub collection: Account<'info, Collection>,
+    #[account(mut, has_one = collection, seeds = [collection.key().as_ref(), b"metadata".as_ref(), &asset.collection_num.to_le_bytes()], bump)]
+    pub asset: Account<'info, Metadata>,
 }
 
 #[derive(Accounts)]
@@ -405,3 +1239,626 @@ pub struct CudUpdate {
 pub struct CudDelete {
     asset_id: Pubkey,
 }
+
+#[event]
+pub struct NftTransferred {
+    asset: Pubkey,
+    from: Pubkey,
+    to: Pubkey,
+    authority: Pubkey,
+}
+
+#[event]
+pub struct NftDelegated {
+    asset: Pubkey,
+    owner: Pubkey,
+    delegate: Pubkey,
+    authority: Pubkey,
+}
+
+#[event]
+pub struct NftRevoked {
+    asset: Pubkey,
+    owner: Pubkey,
+    delegate: Pubkey,
+    authority: Pubkey,
+}
+
+// This is a copy-paste from `additional-accounts-request` crate, needed
+// to make sure that we can deserialize the return data in
+// our typescript client
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct ExternalIAccountMeta {
+    pub pubkey: Pubkey,
+    pub signer: bool,
+    pub writable: bool,
+}
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct ITransfer<'info> {
+    /// CHECK:
+    pub owner: AccountInfo<'info>,
+    /// CHECK:
+    pub destination: AccountInfo<'info>,
+    /// CHECK:
+    pub authority: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct IDelegate<'info> {
+    /// CHECK:
+    pub owner: AccountInfo<'info>,
+    /// CHECK:
+    pub delegate: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct IRevoke<'info> {
+    /// CHECK:
+    pub owner: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+/// Interface Preflight
+#[derive(Accounts)]
+pub struct IBurn<'info> {
+    /// CHECK:
+    pub authority: AccountInfo<'info>,
+    /// CHECK:
+    pub rent_recipient: AccountInfo<'info>,
+    /// CHECK:
+    pub asset: Account<'info, Metadata>,
+}
+
+#[error_code]
+pub enum NftStyleTwoError {
+    #[msg("Metadata can list at most 5 creators")]
+    TooManyCreators,
+    #[msg("Creator shares must add up to 10000 basis points")]
+    InvalidCreatorShares,
+    #[msg("A creator is listed more than once")]
+    DuplicateCreator,
+    #[msg("Signer is not a creator of the asset")]
+    UnknownCreator,
+    #[msg("Signer is not the owner of the asset")]
+    NotOwner,
+    #[msg("Owner account does not match the asset's owner")]
+    OwnerMismatch,
+    #[msg("Only the master edition's owner or authority can print editions")]
+    NotMasterEditionHolder,
+    #[msg("Edition number must be between 1 and the master edition's max_supply")]
+    EditionNumOutOfRange,
+    #[msg("Master edition has printed its max_supply")]
+    MaxSupplyReached,
+    #[msg("Master edition max_supply cannot exceed the edition metadata's num_versions")]
+    InvalidMaxSupply,
+    #[msg("Master edition cannot be burned while its prints exist")]
+    MasterEditionHasPrints,
+    #[msg("Master edition supply would underflow")]
+    SupplyUnderflow,
+    #[msg("Signer is neither the owner nor the delegate of the asset")]
+    NotOwnerOrDelegate,
+    #[msg("Metadata is not in the first released layout")]
+    NotLegacyMetadata,
+    #[msg("Edition does not belong to this metadata")]
+    EditionMismatch,
+    #[msg("Master edition is not in the first released layout")]
+    NotLegacyMasterEdition,
+    #[msg(
+        "Supply can't exceed the prints before next_edition, which can be at most max_supply + 1"
+    )]
+    InvalidEditionCounts,
+}
+
+impl From<CreatorError> for NftStyleTwoError {
+    fn from(e: CreatorError) -> Self {
+        match e {
+            CreatorError::TooManyCreators => NftStyleTwoError::TooManyCreators,
+            CreatorError::InvalidShares => NftStyleTwoError::InvalidCreatorShares,
+            CreatorError::DuplicateCreator => NftStyleTwoError::DuplicateCreator,
+            CreatorError::UnknownCreator => NftStyleTwoError::UnknownCreator,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nft_events::{validate_asset_group, AssetGroupContext};
+
+    /// `sha256("srfc19:collection")[..8]`
+    const COLLECTION_DISC: [u8; 8] = [0x32, 0xbb, 0x95, 0x3a, 0xa9, 0xfe, 0x9b, 0xfb];
+    /// `sha256("srfc19:metadata")[..8]`
+    const METADATA_DISC: [u8; 8] = [0xbc, 0xe6, 0x61, 0xf0, 0x4e, 0xb7, 0x42, 0x8d];
+    /// Spec v1 header without flags, and with `FLAG_TLV_PAYLOAD`
+    const V1: [u8; 2] = [0x81, 0x00];
+    const V1_TLV: [u8; 2] = [0x81, 0x01];
+
+    fn key(seed: u8) -> Pubkey {
+        Pubkey::new_from_array([seed; 32])
+    }
+
+    fn metadata() -> Metadata {
+        Metadata {
+            collection: key(1),
+            collection_num: 0,
+            edition_metadata: key(2),
+            edition: key(3),
+            owner: key(4),
+            delegate: key(4),
+            name: "Mona".to_string(),
+            symbol: "ML".to_string(),
+            uri: "ar://x".to_string(),
+            creators: vec![],
+        }
+    }
+
+    fn assert_conforms(group: &nft_events::AssetGroup, owner: Pubkey) {
+        let ctx = AssetGroupContext {
+            program_id: crate::ID,
+            owner: Some(owner),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_asset_group(&group.asset_id, &group.pubkeys, &group.data, &ctx),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn preflight_skips_event_accounts_when_logging() {
+        let payload = preflight_asset_accounts(&metadata(), &crate::ID, true).unwrap();
+        let accounts = PreflightPayload::try_from_slice(&payload).unwrap().accounts;
+
+        let keys: Vec<Pubkey> = accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(keys[..3], [key(1), key(2), key(3)]);
+        assert!(accounts[2].writable);
+        if cfg!(feature = "log-events") {
+            assert_eq!(accounts.len(), 3);
+        } else {
+            assert_eq!(accounts.len(), 5);
+            assert_eq!(keys[4], crate::ID);
+        }
+    }
+
+    #[test]
+    fn collection_group_golden() {
+        let collection = Collection {
+            authority: key(9),
+            name: "Art".to_string(),
+            symbol: "A".to_string(),
+            num_items: 10,
+        };
+        let group = collection.to_asset_group(key(1)).unwrap();
+
+        assert_eq!(group.pubkeys, vec![key(1)]);
+        assert_eq!(group.authority, key(9));
+        assert_eq!(
+            group.data,
+            [
+                &COLLECTION_DISC[..],
+                &V1_TLV,
+                b"\x04name\x03\x00Art",
+                b"\x06symbol\x01\x00A",
+            ]
+            .concat()
+        );
+        assert_conforms(&group, key(9));
+    }
+
+    #[test]
+    fn edition_metadata_group_golden() {
+        let edition_metadata = EditionMetadata {
+            authority: key(9),
+            title: "Ed".to_string(),
+            description: "Hi".to_string(),
+            num_versions: 10,
+        };
+        let group = edition_metadata.to_asset_group(key(2)).unwrap();
+
+        assert_eq!(group.pubkeys, vec![key(2)]);
+        assert_eq!(
+            group.data,
+            [
+                &COLLECTION_DISC[..],
+                &V1_TLV,
+                b"\x04name\x02\x00Ed",
+                b"\x0bdescription\x02\x00Hi",
+            ]
+            .concat()
+        );
+        assert_conforms(&group, key(9));
+    }
+
+    #[test]
+    fn metadata_group_golden() {
+        let group = metadata().to_asset_group(key(5)).unwrap();
+
+        // Collection, owner, then the edition metadata and edition, without repeating the owner
+        assert_eq!(group.pubkeys, vec![key(1), key(4), key(2), key(3)]);
+        assert_eq!(group.authority, key(4));
+        assert_eq!(
+            group.data,
+            [
+                &METADATA_DISC[..],
+                &V1_TLV,
+                b"\x04name\x04\x00Mona",
+                b"\x06symbol\x02\x00ML",
+                b"\x03uri\x06\x00ar://x",
+                b"\x08creators\x00\x00",
+            ]
+            .concat()
+        );
+        assert_conforms(&group, key(4));
+    }
+
+    #[test]
+    fn emitted_create_golden() {
+        // Captures what `emit_cpi!` would be handed, i.e. the self-CPI data after `EVENT_IX_TAG_LE`
+        let emitted = std::cell::RefCell::new(vec![]);
+        let emitter = nft_events::EventEmitter::Cpi(Box::new(|event| {
+            emitted.borrow_mut().push(event.0);
+            Ok(())
+        }));
+        metadata().emit_create(key(5), &emitter).unwrap();
+        drop(emitter);
+
+        let len = |n: u32| n.to_le_bytes();
+        let data = [
+            &METADATA_DISC[..],
+            &V1_TLV,
+            b"\x04name\x04\x00Mona",
+            b"\x06symbol\x02\x00ML",
+            b"\x03uri\x06\x00ar://x",
+            b"\x08creators\x00\x00",
+        ]
+        .concat();
+        assert_eq!(
+            emitted.into_inner(),
+            vec![[
+                // `sha256("event:CudCreate")[..8]`
+                &[0x5a, 0xe7, 0xa9, 0x4f, 0xc4, 0xd5, 0x32, 0x13][..],
+                key(5).as_ref(),
+                key(4).as_ref(),
+                &len(4),
+                key(1).as_ref(),
+                key(4).as_ref(),
+                key(2).as_ref(),
+                key(3).as_ref(),
+                &len(data.len() as u32),
+                &data,
+            ]
+            .concat()]
+        );
+    }
+
+    #[test]
+    fn edition_group_golden() {
+        let group = metadata()
+            .edition_asset(key(5))
+            .to_asset_group(key(3))
+            .unwrap();
+
+        // Edition metadata, owner, then the collection and metadata
+        assert_eq!(group.pubkeys, vec![key(2), key(4), key(1), key(5)]);
+        assert_eq!(group.authority, key(4));
+        assert_eq!(group.data, [&METADATA_DISC[..], &V1].concat());
+        assert_conforms(&group, key(4));
+    }
+
+    #[test]
+    fn transfer_moves_owner_in_both_groups() {
+        let mut asset = metadata();
+        asset.transfer_to(key(6));
+
+        let group = asset.to_asset_group(key(5)).unwrap();
+        assert_eq!(group.pubkeys[1], key(6));
+        assert_conforms(&group, key(6));
+        let group = asset.edition_asset(key(5)).to_asset_group(key(3)).unwrap();
+        assert_eq!(group.pubkeys[1], key(6));
+        assert_conforms(&group, key(6));
+    }
+
+    #[test]
+    fn delegate_is_second_key_in_both_groups() {
+        let mut asset = metadata();
+        asset.set_delegate(key(7));
+
+        let group = asset.to_asset_group(key(5)).unwrap();
+        assert_eq!(group.pubkeys, vec![key(1), key(7), key(2), key(3)]);
+        assert_eq!(group.authority, key(4));
+        assert_conforms(&group, key(7));
+        let group = asset.edition_asset(key(5)).to_asset_group(key(3)).unwrap();
+        assert_eq!(group.pubkeys, vec![key(2), key(7), key(1), key(5)]);
+        assert_conforms(&group, key(7));
+
+        asset.revoke();
+        let group = asset.to_asset_group(key(5)).unwrap();
+        assert_eq!(group.pubkeys[1], key(4));
+    }
+
+    #[test]
+    fn owner_or_delegate_can_transfer() {
+        let (owner, delegate) = (key(4), key(7));
+        let mut asset = metadata();
+        asset.check_owner_or_delegate(&owner).unwrap();
+        assert_eq!(
+            asset.check_owner_or_delegate(&delegate),
+            Err(NftStyleTwoError::NotOwnerOrDelegate.into())
+        );
+
+        asset.set_delegate(delegate);
+        assert!(asset.is_delegated());
+        asset.check_owner_or_delegate(&delegate).unwrap();
+
+        // Transfers clear the delegation
+        asset.transfer_to(key(6));
+        assert!(!asset.is_delegated());
+        for stale in [owner, delegate] {
+            assert_eq!(
+                asset.check_owner_or_delegate(&stale),
+                Err(NftStyleTwoError::NotOwnerOrDelegate.into())
+            );
+        }
+    }
+
+    #[test]
+    fn migrates_legacy_metadata() {
+        // The first released layout, sized exactly like its `init`
+        let mut legacy = Metadata::DISCRIMINATOR.to_vec();
+        legacy.extend_from_slice(key(4).as_ref());
+        for field in ["Mona", "ML", "ar://x"] {
+            legacy.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            legacy.extend_from_slice(field.as_bytes());
+        }
+        assert_eq!(legacy.len(), 8 + 32 + 4 + 4 + 4 + 2 + 4 + 6);
+
+        let migrated = Metadata::from_legacy(&legacy, key(1), 0, key(2), key(3)).unwrap();
+        assert!(!migrated.is_delegated());
+        let mut serialized = Vec::new();
+        migrated.try_serialize(&mut serialized).unwrap();
+        let mut current = Vec::new();
+        metadata().try_serialize(&mut current).unwrap();
+        assert_eq!(serialized, current);
+        assert_eq!(
+            serialized.len(),
+            Metadata::space("Mona", "ML", "ar://x", &[])
+        );
+
+        assert_eq!(
+            Metadata::from_legacy(&serialized, key(1), 0, key(2), key(3)).unwrap_err(),
+            NftStyleTwoError::NotLegacyMetadata.into()
+        );
+        assert_eq!(
+            Metadata::from_legacy(&legacy[..legacy.len() - 1], key(1), 0, key(2), key(3))
+                .unwrap_err(),
+            NftStyleTwoError::NotLegacyMetadata.into()
+        );
+        assert_eq!(
+            Metadata::from_legacy(&legacy[8..], key(1), 0, key(2), key(3)).unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+
+    #[test]
+    fn legacy_editions_belong_to_their_edition_metadata() {
+        let edition_metadata = EditionMetadata {
+            authority: key(9),
+            title: "Ed".to_string(),
+            description: "Hi".to_string(),
+            num_versions: 10,
+        };
+        let print_key = |version: u32| {
+            Pubkey::find_program_address(
+                &[key(2).as_ref(), b"version".as_ref(), &version.to_le_bytes()],
+                &crate::ID,
+            )
+            .0
+        };
+        let master_key = Pubkey::find_program_address(
+            &[key(2).as_ref(), b"master_edition".as_ref()],
+            &crate::ID,
+        )
+        .0;
+        let check = |address: Pubkey, mut data: Vec<u8>| {
+            let mut lamports = 0;
+            let info = AccountInfo::new(
+                &address,
+                false,
+                false,
+                &mut lamports,
+                &mut data,
+                &crate::ID,
+                false,
+                0,
+            );
+            legacy_edition(&info, &key(2), &edition_metadata, &crate::ID)
+        };
+        let mismatch = Err(NftStyleTwoError::EditionMismatch.into());
+
+        // First release prints were sized like the edition metadata's strings
+        let mut print = Vec::new();
+        Edition {
+            authority: key(4),
+            version: 3,
+        }
+        .try_serialize(&mut print)
+        .unwrap();
+        assert_eq!(print.len(), Edition::LEN);
+        assert_eq!(check(print_key(3), print.clone()), mismatch);
+        print.resize(edition_metadata.legacy_edition_len(), 0);
+        let legacy_print = LegacyEdition {
+            authority: key(4),
+            is_master: false,
+        };
+        assert_eq!(check(print_key(3), print.clone()), Ok(legacy_print));
+        assert_eq!(check(print_key(4), print), mismatch);
+
+        // First release master editions only stored their authority
+        let mut master = MasterEdition::DISCRIMINATOR.to_vec();
+        master.extend_from_slice(key(4).as_ref());
+        master.resize(edition_metadata.legacy_edition_len(), 0);
+        let legacy_master = LegacyEdition {
+            authority: key(4),
+            is_master: true,
+        };
+        assert_eq!(check(master_key, master.clone()), Ok(legacy_master));
+        assert_eq!(check(print_key(0), master), mismatch);
+        let mut migrated = Vec::new();
+        master_edition(key(4), 10)
+            .try_serialize(&mut migrated)
+            .unwrap();
+        assert_eq!(check(master_key, migrated), mismatch);
+    }
+
+    #[test]
+    fn legacy_claims_keep_master_editions_with_their_minter() {
+        let mut owned = metadata();
+        let print = LegacyEdition {
+            authority: key(4),
+            is_master: false,
+        };
+        let master = LegacyEdition {
+            authority: key(4),
+            is_master: true,
+        };
+
+        // Owners claim what they minted, and the edition metadata's authority anything else
+        // but a master edition that changed hands
+        for edition in [&print, &master] {
+            check_legacy_claim(edition, &owned, &key(4), &key(9)).unwrap();
+            check_legacy_claim(edition, &owned, &key(9), &key(9)).unwrap();
+            assert_eq!(
+                check_legacy_claim(edition, &owned, &key(5), &key(9)),
+                Err(NftStyleTwoError::NotOwner.into())
+            );
+        }
+        owned.owner = key(5);
+        check_legacy_claim(&print, &owned, &key(9), &key(9)).unwrap();
+        for (edition, signer) in [(&master, key(9)), (&master, key(5)), (&print, key(5))] {
+            assert_eq!(
+                check_legacy_claim(edition, &owned, &signer, &key(9)),
+                Err(NftStyleTwoError::EditionMismatch.into())
+            );
+        }
+    }
+
+    fn master_edition(authority: Pubkey, max_supply: u32) -> MasterEdition {
+        MasterEdition {
+            authority,
+            supply: 0,
+            max_supply,
+            next_edition: 1,
+        }
+    }
+
+    #[test]
+    fn migrates_legacy_master_editions() {
+        let edition_metadata = EditionMetadata {
+            authority: key(9),
+            title: "Ed".to_string(),
+            description: "Hi".to_string(),
+            num_versions: 10,
+        };
+        // The first released layout, sized like the edition metadata's strings
+        let mut legacy = MasterEdition::DISCRIMINATOR.to_vec();
+        legacy.extend_from_slice(key(4).as_ref());
+        legacy.resize(8 + 32 + 4 + 2 + 4 + 2, 0);
+
+        let mut migrated = MasterEdition::from_legacy(&legacy, &edition_metadata).unwrap();
+        assert_eq!(migrated.authority, key(4));
+        for (max_supply, supply, next_edition) in [(5, 0, 0), (5, 0, 7), (5, 3, 3)] {
+            assert_eq!(
+                migrated.set_counts(max_supply, supply, next_edition),
+                Err(NftStyleTwoError::InvalidEditionCounts.into())
+            );
+        }
+        migrated.set_counts(5, 2, 4).unwrap();
+        // Prints minted before the upgrade can be burned, and printing continues
+        migrated.record_burn().unwrap();
+        migrated.check_print(&key(4), &key(4), 4).unwrap();
+        migrated.record_print(4).unwrap();
+        assert_eq!((migrated.supply, migrated.next_edition), (2, 5));
+
+        let mut current = Vec::new();
+        migrated.try_serialize(&mut current).unwrap();
+        assert_eq!(current.len(), MasterEdition::LEN);
+        let mut unprinted = Vec::new();
+        master_edition(key(4), 0)
+            .try_serialize(&mut unprinted)
+            .unwrap();
+        for data in [&current, &unprinted, &legacy[..legacy.len() - 1]] {
+            assert_eq!(
+                MasterEdition::from_legacy(data, &edition_metadata).unwrap_err(),
+                NftStyleTwoError::NotLegacyMasterEdition.into()
+            );
+        }
+        assert_eq!(
+            MasterEdition::from_legacy(&legacy[8..], &edition_metadata).unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+
+    #[test]
+    fn only_holder_or_authority_prints() {
+        let (authority, holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let master = master_edition(authority, 10);
+
+        master.check_print(&authority, &holder, 1).unwrap();
+        master.check_print(&holder, &holder, 1).unwrap();
+        assert_eq!(
+            master.check_print(&Pubkey::new_unique(), &holder, 1),
+            Err(NftStyleTwoError::NotMasterEditionHolder.into())
+        );
+    }
+
+    #[test]
+    fn prints_stay_within_max_supply() {
+        let authority = Pubkey::new_unique();
+        let mut master = master_edition(authority, 2);
+        for edition_num in [0, 3, u32::MAX] {
+            assert_eq!(
+                master.check_print(&authority, &authority, edition_num),
+                Err(NftStyleTwoError::EditionNumOutOfRange.into())
+            );
+        }
+
+        master.record_print(master.next_edition).unwrap();
+        master.record_print(master.next_edition).unwrap();
+        assert_eq!((master.supply, master.next_edition), (2, 3));
+        assert_eq!(
+            master.check_print(&authority, &authority, 1),
+            Err(NftStyleTwoError::MaxSupplyReached.into())
+        );
+
+        master.record_burn().unwrap();
+        master.check_print(&authority, &authority, 1).unwrap();
+        assert_eq!(
+            master.check_print(&authority, &authority, master.next_edition),
+            Err(NftStyleTwoError::EditionNumOutOfRange.into())
+        );
+    }
+
+    #[test]
+    fn explicit_prints_advance_next_edition() {
+        let mut master = master_edition(Pubkey::new_unique(), 10);
+        master.record_print(5).unwrap();
+        assert_eq!(master.next_edition, 6);
+        master.record_print(2).unwrap();
+        assert_eq!((master.supply, master.next_edition), (2, 6));
+
+        master.supply = 0;
+        assert_eq!(
+            master.record_burn(),
+            Err(NftStyleTwoError::SupplyUnderflow.into())
+        );
+    }
+}
@@ -71,9 +71,12 @@ export class GIndexer {
   }
 
   async handleTransaction(tx: anchor.web3.TransactionResponse) {
-    let cpiEvents = parseCpiEvents(tx, this.program);
+    // Programs built with `log-events` write to the logs instead of self-CPIs
+    let events = parseCpiEvents(tx, this.program).concat(
+      parseLogEvents(tx, this.program)
+    );
 
-    for (const event of cpiEvents) {
+    for (const event of events) {
       this.handleCpiEvent(event);
     }
   }
@@ -86,6 +89,11 @@ export class GIndexer {
       await this.handleCUDUpdate(event);
     } else if (event.name === "CudDelete") {
       await this.handleCUDDelete(event);
+    } else if (
+      event.name === "CudCreateBatch" ||
+      event.name === "CudUpdateBatch"
+    ) {
+      await this.handleCUDBatch(event);
     }
   }
 
@@ -97,6 +105,12 @@ export class GIndexer {
     const data = event.data as CudCreate;
     await this.upsertAsset(jsonifyAssetGroup(data)); // JSON ?
   }
+  private async handleCUDBatch(event: anchor.Event) {
+    const data = event.data as CudBatch;
+    for (const assetGroup of data.assetGroups) {
+      await this.upsertAsset(jsonifyAssetGroup(assetGroup));
+    }
+  }
   private async handleCUDDelete(event: anchor.Event) {
     const data = event.data as CudDelete;
     await this.deleteAsset(data.assetId.toBase58());
@@ -174,6 +188,45 @@ type CudUpdate = CudCreate;
 type CudDelete = {
   assetId: PublicKey;
 };
+type CudBatch = {
+  assetGroups: IAssetGroup[];
+};
+
+// Batch events are declared in `nft-events` rather than in each program,
+// so they are not part of program IDLs and are decoded with this one instead
+const BATCH_EVENTS_IDL: anchor.Idl = {
+  version: "0.1.0",
+  name: "nft_events",
+  instructions: [],
+  types: [
+    {
+      name: "AssetGroup",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "assetId", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "pubkeys", type: { vec: "publicKey" } },
+          { name: "data", type: "bytes" },
+        ],
+      },
+    },
+  ],
+  events: ["CudCreateBatch", "CudUpdateBatch"].map((name) => {
+    return {
+      name,
+      fields: [
+        {
+          name: "assetGroups",
+          type: { vec: { defined: "AssetGroup" } },
+          index: false,
+        },
+      ],
+    };
+  }),
+};
+const batchEventCoder = new anchor.BorshEventCoder(BATCH_EVENTS_IDL);
+const batchCoder = new anchor.BorshCoder(BATCH_EVENTS_IDL);
 
 function jsonifyAssetGroup(data: IAssetGroup) {
   return {
@@ -236,7 +289,7 @@ const CPI_EVENT_IX: Buffer = Buffer.from([
 ]);
 
 // Parses CPI events from a transaction for the given anchor program
-function parseCpiEvents(
+export function parseCpiEvents(
   tx: anchor.web3.TransactionResponse,
   program: anchor.Program
 ): anchor.Event[] {
@@ -262,10 +315,31 @@ function parseCpiEvents(
       }
 
       const eventData = anchor.utils.bytes.base64.encode(ix.data.slice(8));
-      const event = program.coder.events.decode(eventData);
-      events.push(event);
+      const event =
+        program.coder.events.decode(eventData) ??
+        batchEventCoder.decode(eventData);
+      if (event) {
+        events.push(event);
+      }
     }
   }
 
   return events;
 }
+
+// Parses events written with `sol_log_data` by the given anchor program
+export function parseLogEvents(
+  tx: anchor.web3.TransactionResponse,
+  program: anchor.Program
+): anchor.Event[] {
+  const logs = tx.meta?.logMessages ?? [];
+
+  let events: anchor.Event[] = [];
+  for (const coder of [program.coder, batchCoder]) {
+    const parser = new anchor.EventParser(program.programId, coder);
+    for (const event of parser.parseLogs(logs)) {
+      events.push(event);
+    }
+  }
+  return events;
+}
@@ -1,15 +1,18 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { NftStyleOne } from "../target/types/nft_style_one";
+import { NftStyleTwo } from "../target/types/nft_style_two";
 import { Marketplace } from "../target/types/marketplace";
 import { LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
 import { additionalAccountsRequest } from "./additionalAccountsRequest";
+import { assert } from "chai";
 
 describe("marketplace.e2e", () => {
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const marketplace = anchor.workspace.Marketplace as Program<Marketplace>;
   const nftProgram = anchor.workspace.NftStyleOne as Program<NftStyleOne>;
+  const styleTwo = anchor.workspace.NftStyleTwo as Program<NftStyleTwo>;
 
   let provider = marketplace.provider;
 
@@ -29,7 +32,7 @@ describe("marketplace.e2e", () => {
 
     before(async () => {
       await nftProgram.methods
-        .initCollection(10000)
+        .initCollection(10000, "cname", "csymbol", "curi")
         .accounts({
           owner: provider.publicKey!,
           collection,
@@ -40,7 +43,7 @@ describe("marketplace.e2e", () => {
       /// TODO(ngundotra): mint a different # in the collection
       /// if this has already been taken
       await nftProgram.methods
-        .mint(0, "hello", "WOW", "www.google.com")
+        .mint(0, "hello", "WOW", "www.google.com", [], [])
         .accounts({
           owner: provider.publicKey!,
           asset: metadata,
@@ -113,4 +116,102 @@ describe("marketplace.e2e", () => {
       });
     });
   });
+
+  describe("NFT Style Two", () => {
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let editionMetadataKp = anchor.web3.Keypair.generate();
+    let editionMetadata = editionMetadataKp.publicKey;
+    let metadata = PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(0).toArray("le", 4)),
+      ],
+      styleTwo.programId
+    )[0];
+
+    let listingPrice = new anchor.BN(20000);
+    let listing = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("listing"),
+        styleTwo.programId.toBuffer(),
+        metadata.toBuffer(),
+        Buffer.from(listingPrice.toArray("le", 8)),
+      ],
+      marketplace.programId
+    )[0];
+
+    before(async () => {
+      await styleTwo.methods
+        .initialize("cname", "csymbol", 10, "ename", "edesc", 10)
+        .accounts({
+          owner: provider.publicKey!,
+          collection,
+          editionMetadata,
+        })
+        .signers([collectionKp, editionMetadataKp])
+        .rpc({ commitment: "confirmed" });
+      await styleTwo.methods
+        .mintMasterEdition(0, 10, "hello", "WOW", "www.google.com", [])
+        .accounts({
+          owner: provider.publicKey!,
+          collection,
+          editionMetadata,
+        })
+        .rpc({ commitment: "confirmed" });
+    });
+
+    it("Can list an NFT", async () => {
+      let ix = await marketplace.methods
+        .list(listingPrice)
+        .accounts({
+          assetOwner: provider.publicKey!,
+          asset: metadata,
+          authority: provider.publicKey!,
+          nftProgram: styleTwo.programId,
+          fundRecipient: provider.publicKey!,
+        })
+        .instruction();
+
+      ix = await additionalAccountsRequest(marketplace, ix, "list");
+
+      let tx = new anchor.web3.Transaction();
+      tx.add(ix);
+      await provider.sendAndConfirm(tx, [], { commitment: "confirmed" });
+
+      let asset = await styleTwo.account.metadata.fetch(metadata);
+      assert(asset.owner.equals(listing), "Listing must escrow the NFT");
+    });
+    it("Can buy an NFT", async () => {
+      let buyerKp = anchor.web3.Keypair.generate();
+      let buyer = buyerKp.publicKey;
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(buyer, LAMPORTS_PER_SOL),
+        "confirmed"
+      );
+
+      let ix = await marketplace.methods
+        .buyListing()
+        .accounts({
+          assetOwner: listing,
+          asset: metadata,
+          buyer,
+          marketplaceListing: listing,
+          nftProgram: styleTwo.programId,
+          fundRecipient: provider.publicKey!,
+        })
+        .signers([buyerKp])
+        .instruction();
+
+      ix = await additionalAccountsRequest(marketplace, ix, "buy_listing");
+
+      let tx = new anchor.web3.Transaction();
+      tx.add(ix);
+      await provider.sendAndConfirm(tx, [buyerKp], { commitment: "confirmed" });
+
+      let asset = await styleTwo.account.metadata.fetch(metadata);
+      assert(asset.owner.equals(buyer), "Buyer must own the NFT");
+    });
+  });
 });
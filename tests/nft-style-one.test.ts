@@ -1,9 +1,10 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { NftStyleOne } from "../target/types/nft_style_one";
-import { GIndexer, createGIndexer } from "./gindexerPg";
+import { GIndexer, createGIndexer, parseCpiEvents } from "./gindexerPg";
 import { NFTRpc } from "./nftRpc";
 import { assert } from "chai";
+import { createHash } from "crypto";
 
 describe("nft-style-one.e2e", () => {
   // Configure the client to use the local cluster.
@@ -22,6 +23,8 @@ describe("nft-style-one.e2e", () => {
     program.programId
   )[0];
 
+  let attributes = [{ traitType: "Background", value: "Blue" }];
+
   let gIndexer: GIndexer;
   let nftRpc: NFTRpc;
 
@@ -32,7 +35,7 @@ describe("nft-style-one.e2e", () => {
   });
   it("Can create a collection", async () => {
     const tx = await program.methods
-      .initCollection(10000)
+      .initCollection(10000, "cname", "csymbol", "curi")
       .accounts({
         owner: program.provider.publicKey,
         collection,
@@ -54,7 +57,7 @@ describe("nft-style-one.e2e", () => {
   });
   it("Can mint an NFT", async () => {
     const tx = await program.methods
-      .mint(0, "name", "symbol", "uri")
+      .mint(0, "name", "symbol", "uri", attributes, [])
       .accounts({
         owner: program.provider.publicKey,
         asset: metadata,
@@ -89,6 +92,35 @@ describe("nft-style-one.e2e", () => {
     assert(nft["name"] === "name", "NFT must have correct name");
     assert(nft["uri"] === "uri", "NFT must have correct uri");
     assert(nft["symbol"] === "symbol", "NFT must have correct symbol");
+    assert.deepEqual(
+      nft["attributes"],
+      [{ trait_type: "Background", value: "Blue" }],
+      "NFT must render its attributes"
+    );
+
+    let tlv = await nftRpc.fetchNFTTlv(
+      new anchor.web3.PublicKey(asset.assetId)
+    );
+    assert(
+      tlv.get("name")?.toString("utf-8") === "name",
+      "TLV render must have correct name"
+    );
+    assert(
+      new anchor.web3.PublicKey(tlv.get("owner")).equals(
+        program.provider.publicKey
+      ),
+      "TLV render must have correct owner"
+    );
+    assert(
+      tlv.get("attr:Background")?.toString("utf-8") === "Blue",
+      "TLV render must have the attributes"
+    );
+
+    let indexed = await gIndexer.fetchAsset(metadata);
+    assert(
+      indexed.data.includes(Buffer.from("attr:Background")),
+      "AssetGroup data must carry the attributes"
+    );
   });
   it("Can transfer an NFT", async () => {
     let randomDestination = anchor.web3.Keypair.generate().publicKey;
@@ -109,6 +141,23 @@ describe("nft-style-one.e2e", () => {
 
     await gIndexer.handleTransaction(txResult);
 
+    let transferred = parseCpiEvents(txResult, program).find(
+      (event) => event.name === "NftTransferred"
+    );
+    assert(transferred, "Transfer must emit NftTransferred");
+    let transferData = transferred.data as {
+      from: anchor.web3.PublicKey;
+      to: anchor.web3.PublicKey;
+    };
+    assert(
+      transferData.from.equals(program.provider.publicKey),
+      "NftTransferred must record the previous owner"
+    );
+    assert(
+      transferData.to.equals(randomDestination),
+      "NftTransferred must record the destination"
+    );
+
     // Test parsing
     // Collection should still only have 1 asset
     let collectionAssets = await nftRpc.fetchNFTsinCollection(collection);
@@ -132,6 +181,723 @@ describe("nft-style-one.e2e", () => {
       "Transferring an NFT Asset must update the delegate as the second key (in this case we set it to dest)"
     );
   });
+  it("Can mint a batch of NFTs", async () => {
+    const startNum = 100;
+    const items = [0, 1, 2].map((i) => {
+      return {
+        name: `name${i}`,
+        symbol: "symbol",
+        uri: `uri${i}`,
+        attributes: [],
+        creators: [],
+      };
+    });
+    const assets = items.map(
+      (_, i) =>
+        anchor.web3.PublicKey.findProgramAddressSync(
+          [
+            collection.toBuffer(),
+            Buffer.from("metadata"),
+            Buffer.from(new anchor.BN(startNum + i).toArray("le", 4)),
+          ],
+          program.programId
+        )[0]
+    );
+
+    const tx = await program.methods
+      .mintBatch(startNum, items)
+      .accounts({
+        owner: program.provider.publicKey,
+        collection,
+      })
+      .remainingAccounts(
+        assets.map((pubkey) => {
+          return { pubkey, isSigner: false, isWritable: true };
+        })
+      )
+      .rpc({ commitment: "confirmed" });
+
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+
+    let events = parseCpiEvents(txResult, program);
+    assert(events.length === 1, "Batch mint must emit a single event");
+    assert(
+      events[0].name === "CudCreateBatch",
+      "Batch mint must emit CudCreateBatch"
+    );
+
+    await gIndexer.handleTransaction(txResult);
+
+    let collectionAssets = await nftRpc.fetchNFTsinCollection(collection);
+    assert(
+      collectionAssets.length === 1 + items.length,
+      "Collection should have every batch minted asset"
+    );
+
+    let nft = await nftRpc.fetchNFT(assets[2]);
+    assert(nft["name"] === "name2", "NFT must have correct name");
+    assert(nft["uri"] === "uri2", "NFT must have correct uri");
+  });
+  it("Can delegate, revoke and transfer as a delegate", async () => {
+    let owner = program.provider.publicKey;
+    let delegateKp = anchor.web3.Keypair.generate();
+    let delegate = delegateKp.publicKey;
+    let asset = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(1).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+
+    await program.methods
+      .mint(1, "name1", "symbol", "uri1", [], [])
+      .accounts({ owner, asset, collection })
+      .rpc({ commitment: "confirmed" });
+
+    async function index(tx: string) {
+      const txResult = await program.provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+      });
+      await gIndexer.handleTransaction(txResult);
+      return txResult;
+    }
+
+    let txResult = await index(
+      await program.methods
+        .delegate()
+        .accounts({ owner, delegate, asset, collection })
+        .rpc({ commitment: "confirmed" })
+    );
+    assert(
+      parseCpiEvents(txResult, program).find(
+        (event) => event.name === "NftDelegated"
+      ),
+      "Delegate must emit NftDelegated"
+    );
+    let indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.pubkeys[1] === delegate.toBase58(),
+      "Delegated NFT Asset must have delegate as second key"
+    );
+
+    txResult = await index(
+      await program.methods
+        .revoke()
+        .accounts({ owner, asset, collection })
+        .rpc({ commitment: "confirmed" })
+    );
+    assert(
+      parseCpiEvents(txResult, program).find(
+        (event) => event.name === "NftRevoked"
+      ),
+      "Revoke must emit NftRevoked"
+    );
+    indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.pubkeys[1] === owner.toBase58(),
+      "Revoked NFT Asset must have owner as second key"
+    );
+
+    await program.methods
+      .delegate()
+      .accounts({ owner, delegate, asset, collection })
+      .rpc({ commitment: "confirmed" });
+    let destination = anchor.web3.Keypair.generate().publicKey;
+    txResult = await index(
+      await program.methods
+        .transfer()
+        .accounts({
+          owner,
+          destination,
+          authority: delegate,
+          collection,
+          asset,
+        })
+        .signers([delegateKp])
+        .rpc({ commitment: "confirmed" })
+    );
+    let transferred = parseCpiEvents(txResult, program).find(
+      (event) => event.name === "NftTransferred"
+    );
+    assert(transferred, "Transfer must emit NftTransferred");
+    assert(transferred.data.from.equals(owner), "Must record the old owner");
+    assert(
+      transferred.data.to.equals(destination),
+      "Must record the new owner"
+    );
+    assert(
+      transferred.data.authority.equals(delegate),
+      "Must record the delegate as the signing authority"
+    );
+    indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.authority === destination.toBase58(),
+      "Delegate must be able to transfer the NFT"
+    );
+    assert(
+      indexed.pubkeys[1] === destination.toBase58(),
+      "Transfer must clear the delegate"
+    );
+  });
+  it("Rejects transfers with a spoofed owner", async () => {
+    let spoofer = anchor.web3.Keypair.generate();
+    try {
+      // The real owner received the asset in "Can transfer an NFT"
+      await program.methods
+        .transfer()
+        .accounts({
+          owner: spoofer.publicKey,
+          destination: spoofer.publicKey,
+          authority: spoofer.publicKey,
+          collection,
+          asset: metadata,
+        })
+        .signers([spoofer])
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Transfer must check the owner account");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "OwnerMismatch");
+    }
+
+    let owner = (await program.account.metadata.fetch(metadata)).owner;
+    try {
+      await program.methods
+        .transfer()
+        .accounts({
+          owner,
+          destination: spoofer.publicKey,
+          authority: spoofer.publicKey,
+          collection,
+          asset: metadata,
+        })
+        .signers([spoofer])
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Transfer must be signed by the owner or delegate");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "NotOwnerOrDelegate");
+    }
+  });
+  it("Can burn an NFT", async () => {
+    let owner = program.provider.publicKey;
+    let asset = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(2).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+
+    await program.methods
+      .mint(2, "name2", "symbol", "uri2", [], [])
+      .accounts({ owner, asset, collection })
+      .rpc({ commitment: "confirmed" });
+    let supply = (await program.account.collection.fetch(collection)).supply;
+
+    const tx = await program.methods
+      .burn()
+      .accounts({ authority: owner, rentRecipient: owner, asset, collection })
+      .rpc({ commitment: "confirmed" });
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+    await gIndexer.handleTransaction(txResult);
+
+    assert(
+      parseCpiEvents(txResult, program).find(
+        (event) => event.name === "CudDelete"
+      ),
+      "Burn must emit CudDelete"
+    );
+    assert(
+      (await program.provider.connection.getAccountInfo(asset)) === null,
+      "Burn must close the metadata account"
+    );
+    assert(
+      (await gIndexer.fetchAsset(asset)) === undefined,
+      "Burned NFT must be removed from the indexer"
+    );
+    assert(
+      (await program.account.collection.fetch(collection)).supply ===
+        supply - 1,
+      "Burn must decrement the collection supply"
+    );
+  });
+  it("Can update and lock metadata", async () => {
+    let authority = program.provider.publicKey;
+    let size = async () =>
+      (await program.provider.connection.getAccountInfo(metadata)).data.length;
+    let before = await size();
+
+    const tx = await program.methods
+      .updateMetadata("a much longer name", "symbol", "uri", attributes)
+      .accounts({ authority, collection, asset: metadata })
+      .rpc({ commitment: "confirmed" });
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+    await gIndexer.handleTransaction(txResult);
+
+    assert(
+      (await size()) === before + "a much longer name".length - "name".length,
+      "Update must realloc the metadata account"
+    );
+    let nft = await nftRpc.fetchNFT(metadata);
+    assert(nft["name"] === "a much longer name", "NFT must be renamed");
+
+    await program.methods
+      .updateMetadata("n", "symbol", "uri", attributes)
+      .accounts({ authority, collection, asset: metadata })
+      .rpc({ commitment: "confirmed" });
+    assert((await size()) === before - 3, "Update must shrink the account");
+
+    await program.methods
+      .lockMetadata()
+      .accounts({ authority, collection, asset: metadata })
+      .rpc({ commitment: "confirmed" });
+    try {
+      await program.methods
+        .updateMetadata("name", "symbol", "uri", [])
+        .accounts({ authority, collection, asset: metadata })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Locked metadata must not change");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "MetadataImmutable");
+    }
+  });
+  it("Can verify a creator", async () => {
+    let owner = program.provider.publicKey;
+    let artist = anchor.web3.Keypair.generate();
+    let asset = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(4).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+    let creators = (ownerShare: number) => [
+      { address: owner, shareBps: ownerShare, verified: false },
+      { address: artist.publicKey, shareBps: 7500, verified: true },
+    ];
+
+    try {
+      await program.methods
+        .mint(4, "name4", "symbol", "uri4", [], creators(2000))
+        .accounts({ owner, asset, collection })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Creator shares must add up to 100%");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "InvalidCreatorShares");
+    }
+
+    await program.methods
+      .mint(4, "name4", "symbol", "uri4", [], creators(2500))
+      .accounts({ owner, asset, collection })
+      .rpc({ commitment: "confirmed" });
+    let nft = await nftRpc.fetchNFT(asset);
+    assert.deepEqual(
+      nft["creators"].map((creator) => creator.verified),
+      [true, false],
+      "Only the minting authority starts out verified"
+    );
+
+    const tx = await program.methods
+      .signCreator()
+      .accounts({ creator: artist.publicKey, collection, asset })
+      .signers([artist])
+      .rpc({ commitment: "confirmed" });
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+    await gIndexer.handleTransaction(txResult);
+
+    nft = await nftRpc.fetchNFT(asset);
+    assert.deepEqual(nft["creators"][1], {
+      address: artist.publicKey.toBase58(),
+      share_bps: 7500,
+      verified: true,
+    });
+    let indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.data.includes(artist.publicKey.toBuffer()),
+      "AssetGroup data must carry the creators"
+    );
+  });
+  it("Rejects unauthorized and out-of-range mints", async () => {
+    let assetFor = (num: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+
+    let intruder = anchor.web3.Keypair.generate();
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(
+        intruder.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      ),
+      "confirmed"
+    );
+    try {
+      await program.methods
+        .mint(3, "name3", "symbol", "uri3", [], [])
+        .accounts({ owner: intruder.publicKey, asset: assetFor(3), collection })
+        .signers([intruder])
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Only the collection authority may mint");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "UnauthorizedMint");
+    }
+
+    try {
+      await program.methods
+        .mint(10000, "name", "symbol", "uri", [], [])
+        .accounts({
+          owner: program.provider.publicKey,
+          asset: assetFor(10000),
+          collection,
+        })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Collection numbers must be below num_items");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "CollectionNumOutOfRange");
+    }
+  });
+  it("Can mint the next NFT of a collection", async () => {
+    let owner = program.provider.publicKey;
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    await program.methods
+      .initCollection(2, "cname", "csymbol", "curi")
+      .accounts({ owner, collection })
+      .signers([collectionKp])
+      .rpc({ commitment: "confirmed" });
+
+    for (let num = 0; num < 2; num++) {
+      let asset = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+      await program.methods
+        .mintNext("name", "symbol", "uri", [], [])
+        .accounts({ owner, asset, collection })
+        .rpc({ commitment: "confirmed" });
+      assert(
+        (await program.account.metadata.fetch(asset)).collectionNum === num,
+        "mintNext must number assets in order"
+      );
+    }
+    let state = await program.account.collection.fetch(collection);
+    assert(state.minted === 2 && state.supply === 2, "Must count mints");
+  });
+  it("Can hand off collection authority", async () => {
+    let owner = program.provider.publicKey;
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let newAuthority = anchor.web3.Keypair.generate();
+    await program.methods
+      .initCollection(1, "cname", "csymbol", "curi")
+      .accounts({ owner, collection })
+      .signers([collectionKp])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .proposeAuthority(newAuthority.publicKey)
+      .accounts({ authority: owner, collection })
+      .rpc({ commitment: "confirmed" });
+    try {
+      await program.methods
+        .acceptAuthority()
+        .accounts({ newAuthority: owner, collection })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Only the pending authority may accept");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "NotPendingAuthority");
+    }
+
+    const tx = await program.methods
+      .acceptAuthority()
+      .accounts({ newAuthority: newAuthority.publicKey, collection })
+      .signers([newAuthority])
+      .rpc({ commitment: "confirmed" });
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+
+    let update = parseCpiEvents(txResult, program).find(
+      (event) => event.name === "CudUpdate"
+    );
+    assert(update, "Accepting must emit a collection CudUpdate");
+    assert(
+      update.data.authority.equals(newAuthority.publicKey),
+      "CudUpdate must carry the new authority"
+    );
+    let state = await program.account.collection.fetch(collection);
+    assert(state.authority.equals(newAuthority.publicKey));
+    assert(state.pendingAuthority === null, "Handoff must be completed");
+
+    let rendered = await nftRpc.fetchNFT(collection);
+    assert(rendered["name"] === "cname", "Collection must render its name");
+  });
+  it("Can public mint with a price, schedule and allowlist", async () => {
+    let owner = program.provider.publicKey;
+    let connection = program.provider.connection;
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let recipient = anchor.web3.Keypair.generate().publicKey;
+    let [buyer, friend, outsider] = [0, 1, 2].map(() =>
+      anchor.web3.Keypair.generate()
+    );
+    for (let wallet of [buyer, outsider]) {
+      await connection.confirmTransaction(
+        await connection.requestAirdrop(
+          wallet.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        ),
+        "confirmed"
+      );
+    }
+
+    // Two-leaf allowlist: leaves are sha256(wallet), nodes hash sorted pairs
+    let sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    let [buyerLeaf, friendLeaf] = [buyer, friend].map((wallet) =>
+      sha256(wallet.publicKey.toBuffer())
+    );
+    let root = sha256(...[buyerLeaf, friendLeaf].sort(Buffer.compare));
+
+    await program.methods
+      .initCollection(10, "cname", "csymbol", "curi")
+      .accounts({ owner, collection })
+      .signers([collectionKp])
+      .rpc({ commitment: "confirmed" });
+    let mintConfig = anchor.web3.PublicKey.findProgramAddressSync(
+      [collection.toBuffer(), Buffer.from("mint_config")],
+      program.programId
+    )[0];
+    let price = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+    let slot = await connection.getSlot("confirmed");
+    await program.methods
+      .initMintConfig({
+        price,
+        recipient,
+        startSlot: new anchor.BN(0),
+        endSlot: new anchor.BN(slot + 10000),
+        walletLimit: 1,
+        allowlistRoot: Array.from(root),
+      })
+      .accounts({ authority: owner, collection, mintConfig })
+      .rpc({ commitment: "confirmed" });
+
+    let publicMint = async (minter: anchor.web3.Keypair, proof: Buffer[]) => {
+      let { minted } = await program.account.collection.fetch(collection);
+      let asset = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(minted).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+      let walletMints = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          mintConfig.toBuffer(),
+          Buffer.from("wallet"),
+          minter.publicKey.toBuffer(),
+        ],
+        program.programId
+      )[0];
+      return await program.methods
+        .publicMint(proof.map((node) => Array.from(node)))
+        .accounts({
+          minter: minter.publicKey,
+          collection,
+          mintConfig,
+          recipient,
+          walletMints,
+          asset,
+        })
+        .signers([minter])
+        .rpc({ commitment: "confirmed" });
+    };
+
+    try {
+      await publicMint(outsider, [friendLeaf]);
+      assert.fail("Wallets off the allowlist must not mint");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "NotAllowlisted");
+    }
+
+    await publicMint(buyer, [friendLeaf]);
+    assert(
+      (await connection.getBalance(recipient, "confirmed")) ===
+        price.toNumber(),
+      "The recipient must be paid the mint price"
+    );
+    let state = await program.account.collection.fetch(collection);
+    assert(state.minted === 1 && state.supply === 1, "Must count mints");
+    let minted = await program.account.metadata.fetch(
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(0).toArray("le", 4)),
+        ],
+        program.programId
+      )[0]
+    );
+    assert.deepEqual(
+      [minted.name, minted.symbol, minted.uri],
+      ["cname #0", "csymbol", "curi"],
+      "Public mints get the collection's placeholder metadata"
+    );
+
+    try {
+      await publicMint(buyer, [friendLeaf]);
+      assert.fail("Wallets must not mint past their limit");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "WalletLimitReached");
+    }
+  });
+  it("Can reveal hidden metadata against a provenance hash", async () => {
+    let owner = program.provider.publicKey;
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let provenance = anchor.web3.PublicKey.findProgramAddressSync(
+      [collection.toBuffer(), Buffer.from("provenance")],
+      program.programId
+    )[0];
+    let assetFor = (num: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+
+    // Two-asset provenance tree with leaves from `reveal_leaf`
+    let sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    let revealed = [
+      { name: "Ape #0", uri: "https://art/0.json" },
+      { name: "Ape #1", uri: "https://art/1.json" },
+    ];
+    let leaves = revealed.map(({ name, uri }, num) =>
+      sha256(
+        Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        Buffer.from(new anchor.BN(name.length).toArray("le", 4)),
+        Buffer.from(name),
+        Buffer.from(uri)
+      )
+    );
+    let hash = sha256(...[...leaves].sort(Buffer.compare));
+
+    await program.methods
+      .initCollection(2, "cname", "csymbol", "curi")
+      .accounts({ owner, collection })
+      .signers([collectionKp])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .commitProvenance(Array.from(hash))
+      .accounts({ authority: owner, collection, provenance })
+      .rpc({ commitment: "confirmed" });
+    for (let num = 0; num < 2; num++) {
+      await program.methods
+        .mintNext("Hidden", "symbol", "https://art/hidden.json", [], [])
+        .accounts({ owner, asset: assetFor(num), collection })
+        .rpc({ commitment: "confirmed" });
+    }
+
+    try {
+      await program.methods
+        .updateMetadata(revealed[1].name, "symbol", revealed[1].uri, [])
+        .accounts({
+          authority: owner,
+          collection,
+          provenance,
+          asset: assetFor(1),
+        })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Hidden metadata must only change by reveal");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "MetadataHidden");
+    }
+
+    let revealAccounts = {
+      authority: owner,
+      collection,
+      provenance,
+      asset: assetFor(0),
+    };
+    try {
+      await program.methods
+        .reveal(revealed[1].name, revealed[1].uri, [Array.from(leaves[0])])
+        .accounts(revealAccounts)
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Reveals must match the committed metadata");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "InvalidRevealProof");
+    }
+
+    const tx = await program.methods
+      .reveal(revealed[0].name, revealed[0].uri, [Array.from(leaves[1])])
+      .accounts(revealAccounts)
+      .rpc({ commitment: "confirmed" });
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+    assert(
+      parseCpiEvents(txResult, program).some(
+        (event) => event.name === "CudUpdate"
+      ),
+      "Revealing must emit a CudUpdate"
+    );
+    let nft = await nftRpc.fetchNFT(assetFor(0));
+    assert(nft["name"] === "Ape #0" && nft["uri"] === "https://art/0.json");
+    assert(nft["revealed"] === true, "Rendered JSON must show the reveal");
+    let tlv = await nftRpc.fetchNFTTlv(assetFor(0));
+    assert(tlv.get("revealed")?.equals(Buffer.from([1])), "And so must TLV");
+    let hidden = await nftRpc.fetchNFT(assetFor(1));
+    assert(hidden["revealed"] === false, "Unrevealed assets must say so");
+    let state = await program.account.provenance.fetch(provenance);
+    assert(state.revealed === 1, "Must count reveals");
+
+    try {
+      await program.methods
+        .reveal(revealed[0].name, revealed[0].uri, [Array.from(leaves[1])])
+        .accounts(revealAccounts)
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Reveals must not be replayed");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "MetadataRevealed");
+    }
+    try {
+      await program.methods
+        .updateMetadata("Not an ape", "symbol", revealed[0].uri, [])
+        .accounts({ authority: owner, collection, asset: assetFor(0) })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Revealed names must not be updated");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "MetadataRevealed");
+    }
+    state = await program.account.provenance.fetch(provenance);
+    assert(state.revealed === 1, "Failed reveals must not be counted");
+  });
   after(async () => {
     console.log("Closing db connection");
     await gIndexer.teardown();
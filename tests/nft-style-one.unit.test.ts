@@ -24,7 +24,7 @@ describe("nft-style-one.unit", () => {
 
   beforeEach(async () => {
     await program.methods
-      .initCollection(10000)
+      .initCollection(10000, "cname", "csymbol", "curi")
       .accounts({
         owner: program.provider.publicKey,
         collection,
@@ -32,7 +32,7 @@ describe("nft-style-one.unit", () => {
       .signers([collectionKp])
       .rpc({ commitment: "confirmed" });
     await program.methods
-      .mint(0, "name", "symbol", "uri")
+      .mint(0, "name", "symbol", "uri", [], [])
       .accounts({
         owner: program.provider.publicKey,
         asset: metadata,
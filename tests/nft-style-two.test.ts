@@ -1,7 +1,9 @@
 import * as anchor from "@coral-xyz/anchor";
+import * as fs from "fs";
+import * as path from "path";
 import { Program } from "@coral-xyz/anchor";
 import { NftStyleTwo } from "../target/types/nft_style_two";
-import { GIndexer, createGIndexer } from "./gindexerPg";
+import { GIndexer, createGIndexer, parseCpiEvents } from "./gindexerPg";
 import { NFTRpc } from "./nftRpc";
 import { assert } from "chai";
 
@@ -15,6 +17,15 @@ describe.skip("nft-style-two", () => {
   let collection = collectionKp.publicKey;
   let editionMetadataKp = anchor.web3.Keypair.generate();
   let editionMetadata = editionMetadataKp.publicKey;
+  // The master edition's metadata, whose owner may print editions
+  let masterMetadata = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      collection.toBuffer(),
+      Buffer.from("metadata"),
+      Buffer.from(new anchor.BN(0).toArray("le", 4)),
+    ],
+    program.programId
+  )[0];
 
   let gIndexer: GIndexer;
   let nftRpc: NFTRpc;
@@ -46,7 +57,7 @@ describe.skip("nft-style-two", () => {
     const nftSymbol = "masterSymbol";
     const nftUri = "masterUri";
     const tx = await program.methods
-      .mintMasterEdition(0, nftName, nftSymbol, nftUri)
+      .mintMasterEdition(0, 10, nftName, nftSymbol, nftUri, [])
       .accounts({
         owner: program.provider.publicKey,
         collection,
@@ -76,6 +87,14 @@ describe.skip("nft-style-two", () => {
       "NFT Asset must have delegate as second key"
     );
 
+    // The master edition is grouped under the edition metadata
+    let editions = await nftRpc.fetchNFTsinCollection(editionMetadata);
+    assert(editions.length === 1, "Edition metadata should have 1 edition");
+    assert(
+      editions[0].pubkeys[1] === program.provider.publicKey.toBase58(),
+      "Edition must have its owner as second key"
+    );
+
     let nft = await nftRpc.fetchNFT(new anchor.web3.PublicKey(asset.assetId));
     console.log(nft);
     assert(nft, "NFT must exist");
@@ -85,11 +104,12 @@ describe.skip("nft-style-two", () => {
   });
   it("Can mint an Edition NFT", async () => {
     const tx = await program.methods
-      .mintEdition(1, 1, "editionName", "editionSymbol", "editionUri")
+      .mintEdition(1, 1, "editionName", "editionSymbol", "editionUri", [])
       .accounts({
         owner: program.provider.publicKey,
         collection,
         editionMetadata,
+        masterMetadata,
       })
       .rpc({ commitment: "confirmed" });
 
@@ -121,15 +141,76 @@ describe.skip("nft-style-two", () => {
     assert(nft["uri"] === "editionUri", "NFT must have correct uri");
     assert(nft["symbol"] === "editionSymbol", "NFT must have correct symbol");
   });
+  it("Can verify a creator", async () => {
+    let artist = anchor.web3.Keypair.generate();
+    const mintTx = await program.methods
+      .mintEdition(2, 2, "editionName", "editionSymbol", "editionUri", [
+        {
+          address: program.provider.publicKey,
+          shareBps: 5000,
+          verified: false,
+        },
+        { address: artist.publicKey, shareBps: 5000, verified: true },
+      ])
+      .accounts({
+        owner: program.provider.publicKey,
+        collection,
+        editionMetadata,
+        masterMetadata,
+      })
+      .rpc({ commitment: "confirmed" });
+    let metadata = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(2).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+    let creators = (await program.account.metadata.fetch(metadata)).creators;
+    assert(creators[0].verified, "The minting creator must be verified");
+    assert(!creators[1].verified, "Other creators must sign for themselves");
+
+    const tx = await program.methods
+      .signCreator()
+      .accounts({ creator: artist.publicKey, collection, asset: metadata })
+      .signers([artist])
+      .rpc({ commitment: "confirmed" });
+    const txResult = await program.provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+    });
+    await gIndexer.handleTransaction(txResult);
+
+    let nft = await nftRpc.fetchNFT(metadata);
+    assert(
+      nft["creators"][1].verified === true,
+      "Signing must verify the creator"
+    );
+  });
   it("Can transfer an NFT", async () => {
     let randomDestination = anchor.web3.Keypair.generate().publicKey;
+    let asset = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(0).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+    let edition = anchor.web3.PublicKey.findProgramAddressSync(
+      [editionMetadata.toBuffer(), Buffer.from("master_edition")],
+      program.programId
+    )[0];
     const tx = await program.methods
-      .transfer(0, 1)
+      .transfer()
       .accounts({
         owner: program.provider.publicKey,
-        dest: randomDestination,
+        destination: randomDestination,
+        authority: program.provider.publicKey,
+        asset,
         collection,
         editionMetadata,
+        edition,
       })
       .rpc({ commitment: "confirmed" });
 
@@ -162,12 +243,56 @@ describe.skip("nft-style-two", () => {
     );
   });
   it("Can burn an NFT", async () => {
+    let owner = program.provider.publicKey;
+    let assetFor = (num: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+    let editionFor = (num: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          editionMetadata.toBuffer(),
+          Buffer.from("version"),
+          Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+    let masterEdition = anchor.web3.PublicKey.findProgramAddressSync(
+      [editionMetadata.toBuffer(), Buffer.from("master_edition")],
+      program.programId
+    )[0];
+
+    try {
+      await program.methods
+        .burn()
+        .accounts({
+          authority: owner,
+          rentRecipient: owner,
+          asset: assetFor(0),
+          collection,
+          editionMetadata,
+          edition: masterEdition,
+        })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Only the owner may burn");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "NotOwner");
+    }
+
     const tx = await program.methods
-      .burn(0, 1)
+      .burn()
       .accounts({
-        owner: program.provider.publicKey,
+        authority: owner,
+        rentRecipient: owner,
+        asset: assetFor(1),
         collection,
         editionMetadata,
+        edition: editionFor(1),
       })
       .rpc({ commitment: "confirmed" });
 
@@ -177,21 +302,283 @@ describe.skip("nft-style-two", () => {
 
     await gIndexer.handleTransaction(txResult);
 
-    // Test parsing
-    // Collection should still only have 1 asset
-    let collectionAssets = await nftRpc.fetchNFTsinCollection(collection);
+    let connection = program.provider.connection;
     assert(
-      collectionAssets.length === 1,
-      "Collection should still only have 1 asset"
+      (await connection.getAccountInfo(assetFor(1), "confirmed")) === null,
+      "Burning must close the metadata"
+    );
+    assert(
+      (await connection.getAccountInfo(editionFor(1), "confirmed")) === null,
+      "Burning must close the edition"
+    );
+    let testWalletAssets = await nftRpc.fetchNFTsForAuthority(owner);
+    assert(
+      !testWalletAssets.some(
+        (asset) => asset.assetId === assetFor(1).toBase58()
+      ),
+      "Burned assets must leave the index"
     );
 
-    let testWalletAssets = await nftRpc.fetchNFTsForAuthority(
-      program.provider.publicKey
+    // The edition number is free to print again
+    await program.methods
+      .mintEdition(1, 1, "editionName", "editionSymbol", "editionUri", [])
+      .accounts({ owner, collection, editionMetadata, masterMetadata })
+      .rpc({ commitment: "confirmed" });
+  });
+  it("Can delegate, revoke and transfer as a delegate", async () => {
+    let owner = program.provider.publicKey;
+    let delegateKp = anchor.web3.Keypair.generate();
+    let delegate = delegateKp.publicKey;
+    let asset = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(1).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+    let edition = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        editionMetadata.toBuffer(),
+        Buffer.from("version"),
+        Buffer.from(new anchor.BN(1).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+    let accounts = { owner, asset, collection, editionMetadata, edition };
+
+    async function index(tx: string) {
+      const txResult = await program.provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+      });
+      await gIndexer.handleTransaction(txResult);
+      return txResult;
+    }
+
+    let txResult = await index(
+      await program.methods
+        .delegate()
+        .accounts({ ...accounts, delegate })
+        .rpc({ commitment: "confirmed" })
     );
-    assert(testWalletAssets.length === 0, "Test wallet should have 0 assets");
+    assert(
+      parseCpiEvents(txResult, program).find(
+        (event) => event.name === "NftDelegated"
+      ),
+      "Delegate must emit NftDelegated"
+    );
+    let indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.pubkeys[1] === delegate.toBase58(),
+      "Delegated NFT Asset must have delegate as second key"
+    );
+    indexed = await gIndexer.fetchAsset(edition);
+    assert(
+      indexed.pubkeys[1] === delegate.toBase58(),
+      "Delegated edition must have delegate as second key"
+    );
+
+    await index(
+      await program.methods
+        .revoke()
+        .accounts(accounts)
+        .rpc({ commitment: "confirmed" })
+    );
+    indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.pubkeys[1] === owner.toBase58(),
+      "Revoked NFT Asset must have owner as second key"
+    );
+
+    await program.methods
+      .delegate()
+      .accounts({ ...accounts, delegate })
+      .rpc({ commitment: "confirmed" });
+    let destination = anchor.web3.Keypair.generate().publicKey;
+    txResult = await index(
+      await program.methods
+        .transfer()
+        .accounts({ ...accounts, destination, authority: delegate })
+        .signers([delegateKp])
+        .rpc({ commitment: "confirmed" })
+    );
+    let transferred = parseCpiEvents(txResult, program).find(
+      (event) => event.name === "NftTransferred"
+    );
+    assert(
+      transferred.data.authority.equals(delegate),
+      "Must record the delegate as the signing authority"
+    );
+    indexed = await gIndexer.fetchAsset(asset);
+    assert(
+      indexed.authority === destination.toBase58(),
+      "Delegate must be able to transfer the NFT"
+    );
+    assert(
+      indexed.pubkeys[1] === destination.toBase58(),
+      "Transfer must clear the delegate"
+    );
+  });
+  it("Can print editions up to the master edition's max supply", async () => {
+    let owner = program.provider.publicKey;
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let editionMetadataKp = anchor.web3.Keypair.generate();
+    let editionMetadata = editionMetadataKp.publicKey;
+    let metadataFor = (num: number) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          collection.toBuffer(),
+          Buffer.from("metadata"),
+          Buffer.from(new anchor.BN(num).toArray("le", 4)),
+        ],
+        program.programId
+      )[0];
+    let masterEdition = anchor.web3.PublicKey.findProgramAddressSync(
+      [editionMetadata.toBuffer(), Buffer.from("master_edition")],
+      program.programId
+    )[0];
+
+    await program.methods
+      .initialize("cname", "csymbol", 10, "ename", "edesc", 10)
+      .accounts({ owner, collection, editionMetadata })
+      .signers([collectionKp, editionMetadataKp])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .mintMasterEdition(0, 2, "master", "symbol", "uri", [])
+      .accounts({ owner, collection, editionMetadata })
+      .rpc({ commitment: "confirmed" });
+
+    let print = (collectionNum: number, printer?: anchor.web3.Keypair) =>
+      program.methods
+        .printEdition(collectionNum, "print", "symbol", "uri", [])
+        .accounts({
+          owner: printer?.publicKey ?? owner,
+          collection,
+          editionMetadata,
+          masterMetadata: metadataFor(0),
+        })
+        .signers(printer ? [printer] : [])
+        .rpc({ commitment: "confirmed" });
+
+    let intruder = anchor.web3.Keypair.generate();
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(
+        intruder.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      ),
+      "confirmed"
+    );
+    try {
+      await print(1, intruder);
+      assert.fail("Only the master edition's holder may print");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "NotMasterEditionHolder");
+    }
+
+    await print(1);
+    await print(2);
+    let master = await program.account.masterEdition.fetch(masterEdition);
+    assert(master.supply === 2 && master.nextEdition === 3);
+    try {
+      await print(3);
+      assert.fail("Prints must not exceed the max supply");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "MaxSupplyReached");
+    }
   });
   after(async () => {
     console.log("Closing db connection");
     await gIndexer.teardown();
   });
 });
+
+// First release accounts, loaded into the validator by Anchor.toml
+describe("nft-style-two.migration", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.NftStyleTwo as Program<NftStyleTwo>;
+  const fixtures = path.join(__dirname, "fixtures", "legacy-style-two");
+  const owner = anchor.web3.Keypair.fromSecretKey(
+    Uint8Array.from(
+      JSON.parse(fs.readFileSync(path.join(fixtures, "owner.json"), "utf-8"))
+    )
+  );
+  const collection = new anchor.web3.PublicKey(
+    "9LQDvLQLXC5eib5cq9Ae2V3e22JGnnvXei6mNwKDmax8"
+  );
+  const editionMetadata = new anchor.web3.PublicKey(
+    "5XphN4op1ynSmtvncdZaP7UAhPFfN9cJAsahKRgiaWh1"
+  );
+  const masterEdition = anchor.web3.PublicKey.findProgramAddressSync(
+    [editionMetadata.toBuffer(), Buffer.from("master_edition")],
+    program.programId
+  )[0];
+  const claim = anchor.web3.PublicKey.findProgramAddressSync(
+    [masterEdition.toBuffer(), Buffer.from("claim")],
+    program.programId
+  )[0];
+  let metadataFor = (num: number) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(num).toArray("le", 4)),
+      ],
+      program.programId
+    )[0];
+  let migrate = (num: number) =>
+    program.methods
+      .migrateMetadata(num)
+      .accounts({
+        authority: owner.publicKey,
+        asset: metadataFor(num),
+        collection,
+        editionMetadata,
+        edition: masterEdition,
+        claim,
+      })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+
+  it("Can migrate legacy metadata and master editions", async () => {
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(
+        owner.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      ),
+      "confirmed"
+    );
+
+    await migrate(0);
+    let metadata = await program.account.metadata.fetch(metadataFor(0));
+    assert(metadata.edition.equals(masterEdition), "Must pair the edition");
+    assert(metadata.delegate.equals(owner.publicKey), "Must be undelegated");
+    assert(metadata.name === "Legacy #0");
+    let state = await program.account.editionClaim.fetch(claim);
+    assert(state.metadata.equals(metadataFor(0)), "Must claim the edition");
+
+    let size = async () =>
+      (await program.provider.connection.getAccountInfo(metadataFor(1))).data
+        .length;
+    let before = await size();
+    try {
+      await migrate(1);
+      assert.fail("An edition must only be claimed once");
+    } catch (err) {
+      assert(
+        err.logs?.some((log: string) => log.includes("already in use")),
+        "The claim must already exist"
+      );
+    }
+    assert((await size()) === before, "Must leave the metadata as it was");
+
+    await program.methods
+      .migrateMasterEdition(10, 0, 1)
+      .accounts({ authority: owner.publicKey, editionMetadata, masterEdition })
+      .signers([owner])
+      .rpc({ commitment: "confirmed" });
+    let master = await program.account.masterEdition.fetch(masterEdition);
+    assert(master.maxSupply === 10 && master.nextEdition === 1);
+  });
+});
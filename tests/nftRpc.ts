@@ -43,6 +43,19 @@ export class NFTRpc {
     return await getAssetData(iasset as IAssetGroup, this.gIndexer.program);
   }
 
+  async fetchNFTTlv(assetId: PublicKey): Promise<Map<string, Buffer>> {
+    let asset = await this.gIndexer.fetchAsset(assetId);
+    let iasset: IAssetGroup = {
+      assetId: new PublicKey(asset.asset_id),
+      authority: new PublicKey(asset.authority),
+      pubkeys: asset.pubkeys.map((key) => new PublicKey(key)),
+      data: TLV_RENDER_DISC,
+    };
+
+    let data = await simulateGetAssetData(iasset, this.gIndexer.program);
+    return decodeTlv(data ?? Buffer.alloc(0));
+  }
+
   async fetchNFTsForAuthority(authority: PublicKey): Promise<AssetGroup[]> {
     let selectAssetGroupQuery = `SELECT * FROM program_assets WHERE 
       program_id = $1 AND authority = $2 AND SUBSTRING(data FROM 1 FOR 8) = $3;`;
@@ -95,10 +108,89 @@ function getMetadataDiscriminator() {
   return METADATA_DISC;
 }
 
+export const TLV_RENDER_DISC = Buffer.from(
+  anchor.utils.sha256.hash("srfc19:render:tlv"),
+  "hex"
+).slice(0, 8);
+
+/**
+ * Decodes `[key_len: u8][key][value_len: u16 LE][value]` entries. Throws on
+ * a truncated entry, like `TlvReader` in nft-events
+ * @param data
+ * @returns
+ */
+export function decodeTlv(data: Buffer): Map<string, Buffer> {
+  let entries = new Map<string, Buffer>();
+  let offset = 0;
+  let take = (len: number): Buffer => {
+    if (offset + len > data.length) {
+      throw new Error(`Truncated TLV entry at offset ${offset}`);
+    }
+    let bytes = data.slice(offset, offset + len);
+    offset += len;
+    return bytes;
+  };
+  while (offset < data.length) {
+    let keyLen = take(1).readUInt8(0);
+    let key = take(keyLen).toString("utf-8");
+    let valueLen = take(2).readUInt16LE(0);
+    entries.set(key, take(valueLen));
+  }
+  return entries;
+}
+
 export async function getAssetData(
   assetGroup: IAssetGroup,
   program: anchor.Program
 ): Promise<Object> {
+  let data = await simulateGetAssetData(assetGroup, program);
+  if (data) {
+    return JSON.parse(data.toString("utf-8"));
+  }
+  return {};
+}
+
+/**
+ * Compute units used by `getAssetData` for the given AssetGroup
+ * @param assetGroup
+ * @param program
+ * @returns
+ */
+export async function getAssetDataComputeUnits(
+  assetGroup: IAssetGroup,
+  program: anchor.Program
+): Promise<number> {
+  let simulationResult = await simulateGetAssetDataTx(assetGroup, program);
+  return simulationResult.unitsConsumed;
+}
+
+async function simulateGetAssetData(
+  assetGroup: IAssetGroup,
+  program: anchor.Program
+): Promise<Buffer | null> {
+  let simulationResult = await simulateGetAssetDataTx(assetGroup, program);
+
+  let returnData = simulationResult.returnData;
+  if (returnData) {
+    let returnDataLog = simulationResult.logs[simulationResult.logs.length - 2];
+    let subjects = returnDataLog.split(" ");
+
+    let programId = subjects[2];
+    let retData = subjects[3];
+
+    if (programId !== program.programId.toBase58()) {
+      throw new Error("Program ID mismatch in return data");
+    }
+
+    return anchor.utils.bytes.base64.decode(retData);
+  }
+  return null;
+}
+
+async function simulateGetAssetDataTx(
+  assetGroup: IAssetGroup,
+  program: anchor.Program
+) {
   let ix = await program.methods
     .getAssetData(assetGroup.data)
     .accounts({ assetId: assetGroup.assetId, authority: assetGroup.authority })
@@ -127,21 +219,5 @@ export async function getAssetData(
   if (!simulationResult) {
     throw new Error("Unable to simulate transaction");
   }
-
-  let returnData = simulationResult.returnData;
-  if (returnData) {
-    let returnDataLog = simulationResult.logs[simulationResult.logs.length - 2];
-    let subjects = returnDataLog.split(" ");
-
-    let programId = subjects[2];
-    let retData = subjects[3];
-
-    if (programId !== program.programId.toBase58()) {
-      throw new Error("Program ID mismatch in return data");
-    }
-
-    let data = anchor.utils.bytes.base64.decode(retData);
-    return JSON.parse(data.toString("utf-8"));
-  }
-  return {};
+  return simulationResult;
 }
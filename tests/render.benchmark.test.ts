@@ -0,0 +1,120 @@
+import * as anchor from "@coral-xyz/anchor";
+import * as fs from "fs";
+import * as path from "path";
+import { Program } from "@coral-xyz/anchor";
+import { NftStyleOne } from "../target/types/nft_style_one";
+import { NftStyleTwo } from "../target/types/nft_style_two";
+import { IAssetGroup } from "./gindexerPg";
+import { TLV_RENDER_DISC, getAssetDataComputeUnits } from "./nftRpc";
+
+// Compute units of `getAssetData`, rendered with the fixed-buffer JSON writer and as TLV.
+// `anchor run benchmark` sets RESULTS_PATH, so plain `anchor test` runs leave the
+// results file alone.
+const RESULTS_PATH = process.env.RENDER_BENCHMARK_RESULTS;
+
+describe("getAssetData.benchmark", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const styleOne = anchor.workspace.NftStyleOne as Program<NftStyleOne>;
+  const styleTwo = anchor.workspace.NftStyleTwo as Program<NftStyleTwo>;
+  const owner = styleOne.provider.publicKey;
+  const rows: string[] = [];
+
+  async function profile(
+    name: string,
+    program: anchor.Program,
+    assetId: anchor.web3.PublicKey,
+    pubkeys: anchor.web3.PublicKey[]
+  ) {
+    let assetGroup: IAssetGroup = {
+      assetId,
+      authority: owner,
+      pubkeys,
+      data: Buffer.alloc(0),
+    };
+    let json = await getAssetDataComputeUnits(assetGroup, program);
+    let tlv = await getAssetDataComputeUnits(
+      { ...assetGroup, data: TLV_RENDER_DISC },
+      program
+    );
+    console.log(name, "json:", json, "tlv:", tlv);
+    rows.push(`| ${program.idl.name} | ${name} | ${json} | ${tlv} |`);
+  }
+
+  after(() => {
+    if (!RESULTS_PATH) {
+      return;
+    }
+    let table = [
+      "# `getAssetData` compute units",
+      "",
+      "Generated by `anchor run benchmark`.",
+      "",
+      "| Program | Account | JSON | TLV |",
+      "| --- | --- | ---: | ---: |",
+      ...rows,
+    ];
+    fs.writeFileSync(path.resolve(RESULTS_PATH), table.join("\n") + "\n");
+  });
+
+  it("nft-style-one", async () => {
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let metadata = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(0).toArray("le", 4)),
+      ],
+      styleOne.programId
+    )[0];
+
+    await styleOne.methods
+      .initCollection(10, "cname", "csymbol", "curi")
+      .accounts({ owner, collection })
+      .signers([collectionKp])
+      .rpc({ commitment: "confirmed" });
+    await styleOne.methods
+      .mint(0, "name", "symbol", "uri", [], [])
+      .accounts({ owner, asset: metadata, collection })
+      .rpc({ commitment: "confirmed" });
+
+    await profile("collection", styleOne, collection, [collection]);
+    await profile("metadata", styleOne, metadata, [collection, owner]);
+  });
+
+  it("nft-style-two", async () => {
+    let collectionKp = anchor.web3.Keypair.generate();
+    let collection = collectionKp.publicKey;
+    let editionMetadataKp = anchor.web3.Keypair.generate();
+    let editionMetadata = editionMetadataKp.publicKey;
+
+    await styleTwo.methods
+      .initialize("cname", "csymbol", 10, "ename", "edesc", 10)
+      .accounts({ owner, collection, editionMetadata })
+      .signers([collectionKp, editionMetadataKp])
+      .rpc({ commitment: "confirmed" });
+    await styleTwo.methods
+      .mintMasterEdition(0, 10, "name", "symbol", "uri", [])
+      .accounts({ owner, collection, editionMetadata })
+      .rpc({ commitment: "confirmed" });
+    let metadata = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        collection.toBuffer(),
+        Buffer.from("metadata"),
+        Buffer.from(new anchor.BN(0).toArray("le", 4)),
+      ],
+      styleTwo.programId
+    )[0];
+    let masterEdition = anchor.web3.PublicKey.findProgramAddressSync(
+      [editionMetadata.toBuffer(), Buffer.from("master_edition")],
+      styleTwo.programId
+    )[0];
+
+    await profile("collection", styleTwo, collection, [collection]);
+    await profile("metadata", styleTwo, metadata, [collection, owner]);
+    await profile("master edition", styleTwo, masterEdition, [
+      editionMetadata,
+    ]);
+  });
+});